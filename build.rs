@@ -0,0 +1,59 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+/*
+ *
+ * Copyright (c) 2022 Universidade de Vigo
+ *
+ * This program is free software; you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License version 2 as
+ * published by the Free Software Foundation;
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program; if not, write to the Free Software
+ * Foundation, Inc., 59 Temple Place, Suite 330, Boston, MA  02111-1307  USA
+ *
+ * Author: Miguel Rodríguez Pérez <miguel@det.uvigo.gal>
+ *
+ */
+
+//! Generates `include/fastgridcache.h` from `src/capi.rs` when the `capi`
+//! feature is enabled, so the header never drifts from the C interface.
+
+fn main() {
+    #[cfg(feature = "capi")]
+    capi::generate_header();
+}
+
+#[cfg(feature = "capi")]
+mod capi {
+    use std::{env, fs, path::Path};
+
+    const HEADER: &str = "include/fastgridcache.h";
+
+    pub fn generate_header() {
+        println!("cargo:rerun-if-changed=src/capi.rs");
+        println!("cargo:rerun-if-changed=cbindgen.toml");
+
+        let dir = env::var("CARGO_MANIFEST_DIR").expect("Cargo sets the manifest directory");
+        let dir = Path::new(&dir);
+        let config = cbindgen::Config::from_file(dir.join("cbindgen.toml"))
+            .expect("Cannot read the cbindgen configuration");
+        let mut header = Vec::new();
+        cbindgen::Builder::new()
+            .with_config(config)
+            .with_src(dir.join("src/capi.rs"))
+            .generate()
+            .expect("Cannot generate the C header")
+            .write(&mut header);
+
+        // Only touch the header when it changes, so packaging sees no edits
+        let path = dir.join(HEADER);
+        if fs::read(&path).ok().as_deref() != Some(&header[..]) {
+            fs::write(&path, header).expect("Cannot write the C header");
+        }
+    }
+}
@@ -0,0 +1,142 @@
+/* SPDX-License-Identifier: GPL-3.0-or-later */
+/*
+ *
+ * Copyright (c) 2022 Universidade de Vigo
+ *
+ * This program is free software; you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License version 2 as
+ * published by the Free Software Foundation;
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program; if not, write to the Free Software
+ * Foundation, Inc., 59 Temple Place, Suite 330, Boston, MA  02111-1307  USA
+ *
+ * Author: Miguel Rodríguez Pérez <miguel@det.uvigo.gal>
+ *
+ */
+
+/*
+ * C interface of fastgridcache. Build the library with the `capi` feature
+ * and link against libfastgridcache.
+ *
+ * Every function returns one of the FGC_* status codes.
+ */
+
+#ifndef FASTGRIDCACHE_H
+#define FASTGRIDCACHE_H
+
+/* Generated from src/capi.rs by build.rs. Do not edit. */
+
+#include <stddef.h>
+#include <stdint.h>
+
+/**
+ * The call succeeded.
+ */
+#define FGC_OK 0
+
+/**
+ * A required pointer was null.
+ */
+#define FGC_NULL_POINTER 1
+
+/**
+ * The arguments do not describe a valid problem or placement.
+ */
+#define FGC_INVALID_ARGUMENT 2
+
+/**
+ * An output buffer is too small for the solution.
+ */
+#define FGC_BUFFER_TOO_SMALL 3
+
+/**
+ * There is no valid placement for the problem.
+ */
+#define FGC_NO_SOLUTION 4
+
+/**
+ * An unexpected internal error happened.
+ */
+#define FGC_INTERNAL_ERROR 5
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Places `nhoriz` horizontal and `nvert` vertical caches in a
+ * `width`×`height` grid, minimising the worst cost when up to `failures`
+ * caches go offline (0 for the nominal cost).
+ *
+ * The positions are written to `horizontal` and `vertical`, that must hold
+ * `nhoriz` and `nvert` elements, and the cost to `cost`. There must be at
+ * least one cache and at most 4 failures.
+ *
+ * # Safety
+ *
+ * The output pointers must be valid for writes of the given sizes.
+ */
+int32_t fgc_get_cache_locations(uint64_t width,
+                                uint64_t height,
+                                size_t nhoriz,
+                                size_t nvert,
+                                size_t failures,
+                                uint64_t *horizontal,
+                                uint64_t *vertical,
+                                uint64_t *cost);
+
+/**
+ * Looks for the best split of `ncaches` between both axes of a
+ * `width`×`height` grid and for its placement, minimising the worst cost when
+ * up to `failures` caches go offline (0 for the nominal cost).
+ *
+ * The positions are written to `horizontal` and `vertical`, that hold
+ * `horizontal_len` and `vertical_len` elements, their number to `nhoriz` and
+ * `nvert`, and the cost to `cost`. Buffers of `ncaches` elements are always
+ * large enough. There must be at least one cache and at most 4 failures.
+ *
+ * # Safety
+ *
+ * The output pointers must be valid for writes of the given sizes.
+ */
+int32_t fgc_find_best_split(uint64_t width,
+                            uint64_t height,
+                            size_t ncaches,
+                            size_t failures,
+                            uint64_t *horizontal,
+                            size_t horizontal_len,
+                            size_t *nhoriz,
+                            uint64_t *vertical,
+                            size_t vertical_len,
+                            size_t *nvert,
+                            uint64_t *cost);
+
+/**
+ * Computes the cost of a placement in a `width`×`height` grid. The positions
+ * in `horizontal` and `vertical` must be strictly increasing and lie inside
+ * the grid.
+ *
+ * # Safety
+ *
+ * `horizontal` and `vertical` must be valid for reads of `nhoriz` and `nvert`
+ * elements, and `cost` for one write.
+ */
+int32_t fgc_get_cost(uint64_t width,
+                     uint64_t height,
+                     const uint64_t *horizontal,
+                     size_t nhoriz,
+                     const uint64_t *vertical,
+                     size_t nvert,
+                     uint64_t *cost);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* FASTGRIDCACHE_H */
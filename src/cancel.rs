@@ -0,0 +1,210 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+/*
+ *
+ * Copyright (c) 2022 Universidade de Vigo
+ *
+ * This program is free software; you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License version 2 as
+ * published by the Free Software Foundation;
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program; if not, write to the Free Software
+ * Foundation, Inc., 59 Temple Place, Suite 330, Boston, MA  02111-1307  USA
+ *
+ * Author: Miguel Rodríguez Pérez <miguel@det.uvigo.gal>
+ *
+ */
+
+//! Searches that can be stopped before they finish.
+
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use crate::{
+    gridsearcher::{search_both, Observer},
+    Objective, Solution,
+};
+
+/// Stops the searches that use it when it is cancelled or when its deadline
+/// passes. Clones share the cancellation, so a clone can be handed to another
+/// thread to cancel the search.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken {
+    cancelled: Arc<AtomicBool>,
+    deadline: Option<Instant>,
+}
+
+impl CancelToken {
+    /// Token that only stops the search when cancelled.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Token that also stops the search at `deadline`.
+    pub fn with_deadline(deadline: Instant) -> Self {
+        Self {
+            deadline: Some(deadline),
+            ..Default::default()
+        }
+    }
+
+    /// Token that also stops the search after `limit` from now.
+    pub fn with_time_limit(limit: Duration) -> Self {
+        Self::with_deadline(Instant::now() + limit)
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    /// Whether the token has been cancelled or its deadline has passed.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+            || self
+                .deadline
+                .is_some_and(|deadline| Instant::now() >= deadline)
+    }
+}
+
+impl<N> Observer<N> for &CancelToken {
+    fn should_stop(&mut self) -> bool {
+        self.is_cancelled()
+    }
+}
+
+/// Stops the searches when its token is cancelled, and remembers whether any
+/// of them was actually stopped before reaching its local optimum.
+#[derive(Debug)]
+pub(crate) struct Watch<'a> {
+    token: &'a CancelToken,
+    interrupted: AtomicBool,
+}
+
+impl<'a> Watch<'a> {
+    pub(crate) fn create(token: &'a CancelToken) -> Self {
+        Self {
+            token,
+            interrupted: AtomicBool::new(false),
+        }
+    }
+
+    /// Whether a search was stopped by the token.
+    pub(crate) fn interrupted(&self) -> bool {
+        self.interrupted.load(Ordering::Relaxed)
+    }
+}
+
+impl<N> Observer<N> for &Watch<'_> {
+    fn should_stop(&mut self) -> bool {
+        let stop = self.token.is_cancelled();
+        if stop {
+            self.interrupted.store(true, Ordering::Relaxed);
+        }
+
+        stop
+    }
+}
+
+/// Solution of a search that might have been stopped before finishing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Outcome<T> {
+    solution: T,
+    complete: bool,
+}
+
+impl<T> Outcome<T> {
+    pub(crate) fn create(solution: T, complete: bool) -> Self {
+        Self { solution, complete }
+    }
+
+    /// Get a reference to the best solution found.
+    pub fn solution(&self) -> &T {
+        &self.solution
+    }
+
+    pub fn into_solution(self) -> T {
+        self.solution
+    }
+
+    /// Whether the search finished. Otherwise, the solution is just the best
+    /// one found before the token stopped it. A token cancelled after the
+    /// search reached its local optimum does not make it incomplete.
+    pub fn is_final(&self) -> bool {
+        self.complete
+    }
+}
+
+/// Like [`get_objective_cache_locations`](crate::gridsearcher::get_objective_cache_locations),
+/// but stopping when `token` is cancelled.
+pub fn get_cache_locations_until(
+    width: u64,
+    height: u64,
+    nhoriz: usize,
+    nvert: usize,
+    objective: Objective,
+    token: &CancelToken,
+) -> Outcome<Solution<u64>> {
+    let watch = Watch::create(token);
+    let sol = search_both(width, height, nhoriz, nvert, objective, |searcher, _| {
+        searcher.find_solution_observed(&mut &watch)
+    });
+
+    Outcome::create(sol, !watch.interrupted())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::{get_cache_locations, get_cost, Objective};
+
+    use super::{get_cache_locations_until, CancelToken};
+
+    #[test]
+    fn cancelled_without_stopping() {
+        // The search ends before the token is ever checked
+        let token = CancelToken::new();
+        token.cancel();
+        let outcome = get_cache_locations_until(4, 3, 1, 0, Objective::Nominal, &token);
+
+        assert!(outcome.is_final());
+        assert_eq!(*outcome.solution(), get_cache_locations(4, 3, 1, 0));
+    }
+
+    #[test]
+    fn finishes_before_deadline() {
+        let token = CancelToken::with_time_limit(Duration::from_secs(60));
+        let outcome = get_cache_locations_until(100, 60, 3, 1, Objective::Nominal, &token);
+
+        assert!(outcome.is_final());
+        assert_eq!(*outcome.solution(), get_cache_locations(100, 60, 3, 1));
+    }
+
+    #[test]
+    fn cancelled_returns_valid_placement() {
+        let token = CancelToken::new();
+        token.cancel();
+        let outcome = get_cache_locations_until(100, 60, 3, 1, Objective::Nominal, &token);
+        let sol = outcome.solution();
+
+        assert!(!outcome.is_final());
+        assert!(sol.cost() > get_cache_locations(100, 60, 3, 1).cost());
+        assert_eq!(
+            sol.cost(),
+            get_cost(
+                &[sol.horizontal_caches(), &[100]].concat(),
+                &[sol.vertical_caches(), &[60]].concat()
+            )
+        );
+    }
+}
@@ -0,0 +1,390 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+/*
+ *
+ * Copyright (c) 2022 Universidade de Vigo
+ *
+ * This program is free software; you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License version 2 as
+ * published by the Free Software Foundation;
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program; if not, write to the Free Software
+ * Foundation, Inc., 59 Temple Place, Suite 330, Boston, MA  02111-1307  USA
+ *
+ * Author: Miguel Rodríguez Pérez <miguel@det.uvigo.gal>
+ *
+ */
+
+//! C interface, built with the `capi` feature. The build script generates
+//! the matching declarations in `include/fastgridcache.h` from this file.
+//!
+//! Every function returns one of the `FGC_*` status codes and never unwinds
+//! into the caller.
+
+use std::{
+    panic::{catch_unwind, UnwindSafe},
+    slice,
+};
+
+use crate::{
+    cost::{get_grid_cost, is_valid_placement},
+    Objective, ProblemDescription,
+};
+
+/// The call succeeded.
+pub const FGC_OK: i32 = 0;
+/// A required pointer was null.
+pub const FGC_NULL_POINTER: i32 = 1;
+/// The arguments do not describe a valid problem or placement.
+pub const FGC_INVALID_ARGUMENT: i32 = 2;
+/// An output buffer is too small for the solution.
+pub const FGC_BUFFER_TOO_SMALL: i32 = 3;
+/// There is no valid placement for the problem.
+pub const FGC_NO_SOLUTION: i32 = 4;
+/// An unexpected internal error happened.
+pub const FGC_INTERNAL_ERROR: i32 = 5;
+
+fn guard<F>(f: F) -> i32
+where
+    F: FnOnce() -> i32 + UnwindSafe,
+{
+    catch_unwind(f).unwrap_or(FGC_INTERNAL_ERROR)
+}
+
+/// Copies `values` into the buffer `dst` of `len` elements.
+///
+/// # Safety
+///
+/// `dst` must be null or valid for writes of `len` elements.
+unsafe fn copy_out(values: &[u64], dst: *mut u64, len: usize) -> Result<(), i32> {
+    if values.is_empty() {
+        return Ok(());
+    }
+    if dst.is_null() {
+        return Err(FGC_NULL_POINTER);
+    }
+    if len < values.len() {
+        return Err(FGC_BUFFER_TOO_SMALL);
+    }
+
+    slice::from_raw_parts_mut(dst, values.len()).copy_from_slice(values);
+    Ok(())
+}
+
+/// Borrows the buffer `src` of `len` elements.
+///
+/// # Safety
+///
+/// `src` must be null or valid for reads of `len` elements.
+unsafe fn borrow_in<'a>(src: *const u64, len: usize) -> Result<&'a [u64], i32> {
+    match (src.is_null(), len) {
+        (_, 0) => Ok(&[]),
+        (true, _) => Err(FGC_NULL_POINTER),
+        (false, len) => Ok(slice::from_raw_parts(src, len)),
+    }
+}
+
+/// Places `nhoriz` horizontal and `nvert` vertical caches in a
+/// `width`×`height` grid, minimising the worst cost when up to `failures`
+/// caches go offline (0 for the nominal cost).
+///
+/// The positions are written to `horizontal` and `vertical`, that must hold
+/// `nhoriz` and `nvert` elements, and the cost to `cost`. There must be at
+/// least one cache and at most 4 failures.
+///
+/// # Safety
+///
+/// The output pointers must be valid for writes of the given sizes.
+#[no_mangle]
+pub unsafe extern "C" fn fgc_get_cache_locations(
+    width: u64,
+    height: u64,
+    nhoriz: usize,
+    nvert: usize,
+    failures: usize,
+    horizontal: *mut u64,
+    vertical: *mut u64,
+    cost: *mut u64,
+) -> i32 {
+    let problem = ProblemDescription {
+        width,
+        height,
+        nhoriz,
+        nvert,
+        objective: Objective::from_failures(failures),
+    };
+    if problem.validate().is_err() {
+        return FGC_INVALID_ARGUMENT;
+    }
+    if cost.is_null() {
+        return FGC_NULL_POINTER;
+    }
+
+    guard(|| {
+        let sol = problem.solve();
+
+        match copy_out(sol.horizontal_caches(), horizontal, nhoriz)
+            .and_then(|_| copy_out(sol.vertical_caches(), vertical, nvert))
+        {
+            Ok(()) => {
+                *cost = sol.cost();
+                FGC_OK
+            }
+            Err(code) => code,
+        }
+    })
+}
+
+/// Looks for the best split of `ncaches` between both axes of a
+/// `width`×`height` grid and for its placement, minimising the worst cost when
+/// up to `failures` caches go offline (0 for the nominal cost).
+///
+/// The positions are written to `horizontal` and `vertical`, that hold
+/// `horizontal_len` and `vertical_len` elements, their number to `nhoriz` and
+/// `nvert`, and the cost to `cost`. Buffers of `ncaches` elements are always
+/// large enough. There must be at least one cache and at most 4 failures.
+///
+/// # Safety
+///
+/// The output pointers must be valid for writes of the given sizes.
+#[no_mangle]
+pub unsafe extern "C" fn fgc_find_best_split(
+    width: u64,
+    height: u64,
+    ncaches: usize,
+    failures: usize,
+    horizontal: *mut u64,
+    horizontal_len: usize,
+    nhoriz: *mut usize,
+    vertical: *mut u64,
+    vertical_len: usize,
+    nvert: *mut usize,
+    cost: *mut u64,
+) -> i32 {
+    let objective = Objective::from_failures(failures);
+    if ncaches == 0 || objective.validate().is_err() {
+        return FGC_INVALID_ARGUMENT;
+    }
+    if nhoriz.is_null() || nvert.is_null() || cost.is_null() {
+        return FGC_NULL_POINTER;
+    }
+
+    guard(|| {
+        let options = crate::SplitOptions::default();
+        let sol = match crate::find_best_split(width, height, ncaches, objective, &options)
+            .expect("Solving without a store never fails")
+        {
+            Some(outcome) => outcome.into_solution(),
+            None => return FGC_NO_SOLUTION,
+        };
+
+        match copy_out(sol.horizontal_caches(), horizontal, horizontal_len)
+            .and_then(|_| copy_out(sol.vertical_caches(), vertical, vertical_len))
+        {
+            Ok(()) => {
+                *nhoriz = sol.horizontal_caches().len();
+                *nvert = sol.vertical_caches().len();
+                *cost = sol.cost();
+                FGC_OK
+            }
+            Err(code) => code,
+        }
+    })
+}
+
+/// Computes the cost of a placement in a `width`×`height` grid. The positions
+/// in `horizontal` and `vertical` must be strictly increasing and lie inside
+/// the grid.
+///
+/// # Safety
+///
+/// `horizontal` and `vertical` must be valid for reads of `nhoriz` and `nvert`
+/// elements, and `cost` for one write.
+#[no_mangle]
+pub unsafe extern "C" fn fgc_get_cost(
+    width: u64,
+    height: u64,
+    horizontal: *const u64,
+    nhoriz: usize,
+    vertical: *const u64,
+    nvert: usize,
+    cost: *mut u64,
+) -> i32 {
+    if cost.is_null() {
+        return FGC_NULL_POINTER;
+    }
+    let (horizontal, vertical) =
+        match borrow_in(horizontal, nhoriz).and_then(|h| Ok((h, borrow_in(vertical, nvert)?))) {
+            Ok(caches) => caches,
+            Err(code) => return code,
+        };
+    if !is_valid_placement(width, height, horizontal, vertical) {
+        return FGC_INVALID_ARGUMENT;
+    }
+
+    guard(|| {
+        *cost = get_grid_cost(width, height, horizontal, vertical);
+        FGC_OK
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ptr::{null, null_mut};
+
+    use super::*;
+
+    #[test]
+    fn get_cache_locations() {
+        let (mut horizontal, mut vertical, mut cost) = ([0; 2], [0; 2], 0);
+        let status = unsafe {
+            fgc_get_cache_locations(
+                100,
+                80,
+                2,
+                2,
+                0,
+                horizontal.as_mut_ptr(),
+                vertical.as_mut_ptr(),
+                &mut cost,
+            )
+        };
+
+        assert_eq!(status, FGC_OK);
+        assert_eq!(horizontal, [44, 74]);
+        assert_eq!(vertical, [29, 58]);
+        assert_eq!(cost, 350_224);
+    }
+
+    #[test]
+    fn find_best_split() {
+        let (mut horizontal, mut vertical) = ([0; 4], [0; 4]);
+        let (mut nhoriz, mut nvert, mut cost) = (0, 0, 0);
+        let status = unsafe {
+            fgc_find_best_split(
+                100,
+                80,
+                4,
+                0,
+                horizontal.as_mut_ptr(),
+                4,
+                &mut nhoriz,
+                vertical.as_mut_ptr(),
+                4,
+                &mut nvert,
+                &mut cost,
+            )
+        };
+
+        assert_eq!(status, FGC_OK);
+        assert_eq!(&horizontal[..nhoriz], [44, 74]);
+        assert_eq!(&vertical[..nvert], [29, 58]);
+        assert_eq!(cost, 350_224);
+    }
+
+    #[test]
+    fn small_buffer() {
+        let (mut horizontal, mut vertical) = ([0; 1], [0; 4]);
+        let (mut nhoriz, mut nvert, mut cost) = (0, 0, 0);
+        let status = unsafe {
+            fgc_find_best_split(
+                100,
+                80,
+                4,
+                0,
+                horizontal.as_mut_ptr(),
+                1,
+                &mut nhoriz,
+                vertical.as_mut_ptr(),
+                4,
+                &mut nvert,
+                &mut cost,
+            )
+        };
+
+        assert_eq!(status, FGC_BUFFER_TOO_SMALL);
+    }
+
+    #[test]
+    fn get_cost() {
+        let (horizontal, vertical, mut cost) = ([32, 54, 77], [20], 0);
+        let status = unsafe {
+            fgc_get_cost(
+                100,
+                40,
+                horizontal.as_ptr(),
+                3,
+                vertical.as_ptr(),
+                1,
+                &mut cost,
+            )
+        };
+
+        assert_eq!(status, FGC_OK);
+        assert_eq!(cost, 114617);
+    }
+
+    #[test]
+    fn invalid_arguments() {
+        let mut cost = 0;
+        let unsorted = [54, 32];
+
+        unsafe {
+            assert_eq!(
+                fgc_get_cost(100, 40, unsorted.as_ptr(), 2, null(), 0, &mut cost),
+                FGC_INVALID_ARGUMENT
+            );
+            assert_eq!(
+                fgc_get_cost(100, 40, null(), 2, null(), 0, &mut cost),
+                FGC_NULL_POINTER
+            );
+            assert_eq!(
+                fgc_get_cache_locations(100, 80, 1, 80, 0, null_mut(), null_mut(), &mut cost),
+                FGC_INVALID_ARGUMENT
+            );
+            assert_eq!(
+                fgc_get_cache_locations(100, 80, 0, 0, 0, null_mut(), null_mut(), &mut cost),
+                FGC_INVALID_ARGUMENT
+            );
+            assert_eq!(
+                fgc_get_cache_locations(
+                    100,
+                    80,
+                    usize::MAX,
+                    1,
+                    0,
+                    null_mut(),
+                    null_mut(),
+                    &mut cost
+                ),
+                FGC_INVALID_ARGUMENT
+            );
+            assert_eq!(
+                fgc_get_cache_locations(100, 80, 2, 2, 5, null_mut(), null_mut(), &mut cost),
+                FGC_INVALID_ARGUMENT
+            );
+            let (mut nhoriz, mut nvert) = (0, 0);
+            assert_eq!(
+                fgc_find_best_split(
+                    100,
+                    80,
+                    0,
+                    0,
+                    null_mut(),
+                    0,
+                    &mut nhoriz,
+                    null_mut(),
+                    0,
+                    &mut nvert,
+                    &mut cost
+                ),
+                FGC_INVALID_ARGUMENT
+            );
+        }
+    }
+}
@@ -0,0 +1,105 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+/*
+ *
+ * Copyright (c) 2022 Universidade de Vigo
+ *
+ * This program is free software; you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License version 2 as
+ * published by the Free Software Foundation;
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program; if not, write to the Free Software
+ * Foundation, Inc., 59 Temple Place, Suite 330, Boston, MA  02111-1307  USA
+ *
+ * Author: Miguel Rodríguez Pérez <miguel@det.uvigo.gal>
+ *
+ */
+
+use crate::Integer;
+
+/// Least sum of the distances from every node of a line of `len` nodes,
+/// starting at the producer, to the closest cache or producer before it when
+/// there are `ncaches` caches. It is reached when caches split the line
+/// evenly.
+fn line_cost<N: Integer>(len: N, ncaches: usize) -> N {
+    let parts = N::from_usize(ncaches + 1);
+    let short = len / parts;
+    let nlong = len - short * parts;
+    let triangle = |n: N| {
+        if n > N::ZERO {
+            n * (n - N::ONE) / N::TWO
+        } else {
+            N::ZERO
+        }
+    };
+
+    nlong * triangle(short + N::ONE) + (parts - nlong) * triangle(short)
+}
+
+/// Lower bound of the cost of any placement of `nhoriz` horizontal and `nvert`
+/// vertical caches in a `width`×`height` quadrant.
+///
+/// The distance from a node to its cache, `x + y - max(a, b)`, is never below
+/// `(x - a) + (y - b)`, where `a` and `b` are the positions of the closest
+/// caches before it in each axis. That separates the cost in two line
+/// problems, which are minimised by even splits, plus the least cost of
+/// reaching the caches.
+///
+/// The bound is coarse: it is only close without vertical caches, and it drops
+/// well below the optimum as soon as both axes have caches, as every node then
+/// counts the savings of both of them.
+pub fn get_lower_bound<N: Integer>(width: N, height: N, nhoriz: usize, nvert: usize) -> N {
+    height * line_cost(width, nhoriz)
+        + width * line_cost(height, nvert)
+        + N::from_usize(nhoriz)
+        + N::from_usize(nvert)
+}
+
+/// Lower bound of the cost of any in-axis placement of `ncaches` caches in a
+/// `width`×`height` quadrant, however they are split between the axes.
+pub fn get_budget_lower_bound<N: Integer>(width: N, height: N, ncaches: usize) -> N {
+    (0..=ncaches)
+        .filter(|&nvert| N::from_usize(ncaches - nvert) < width && N::from_usize(nvert) < height)
+        .map(|nvert| get_lower_bound(width, height, ncaches - nvert, nvert))
+        .min()
+        .unwrap_or(N::ZERO)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{find_best_split, SplitOptions};
+
+    use super::{get_budget_lower_bound, get_lower_bound, line_cost};
+
+    #[test]
+    fn even_line_split() {
+        assert_eq!(line_cost(10u64, 0), 45);
+        assert_eq!(line_cost(10u64, 1), 20);
+        assert_eq!(line_cost(10u64, 2), 3 + 3 + 6);
+    }
+
+    #[test]
+    fn tight_without_vertical_caches() {
+        assert_eq!(get_lower_bound(100u64, 100, 1, 0), 740_001);
+    }
+
+    #[test]
+    fn below_best_split() {
+        for (width, height) in [(100, 80), (100, 50), (60, 60)] {
+            for ncaches in 1..6 {
+                let options = SplitOptions::default();
+                let sol = find_best_split(width, height, ncaches, Default::default(), &options)
+                    .unwrap()
+                    .unwrap()
+                    .into_solution();
+
+                assert!(get_budget_lower_bound(width, height, ncaches) <= sol.cost());
+            }
+        }
+    }
+}
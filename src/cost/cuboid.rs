@@ -0,0 +1,106 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+/*
+ *
+ * Copyright (c) 2022 Universidade de Vigo
+ *
+ * This program is free software; you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License version 2 as
+ * published by the Free Software Foundation;
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program; if not, write to the Free Software
+ * Foundation, Inc., 59 Temple Place, Suite 330, Boston, MA  02111-1307  USA
+ *
+ * Author: Miguel Rodríguez Pérez <miguel@det.uvigo.gal>
+ *
+ */
+
+use itertools::{iproduct, Itertools};
+
+use super::sum_range;
+
+/// Computes the cost of a `width`×`height`×`depth` octant of a three
+/// dimensional lattice with caches on the x, y and z axes.
+///
+/// This generalises [`get_grid_cost`](super::get_grid_cost) to several stacked
+/// grids: every node is served by the closest cache towards the producer, and
+/// the farthest cache of each axis is added to the cost. All the slices must be
+/// sorted and must not include the lattice borders.
+pub fn get_cuboid_cost(
+    width: u64,
+    height: u64,
+    depth: u64,
+    xcaches: &[u64],
+    ycaches: &[u64],
+    zcaches: &[u64],
+) -> u64 {
+    let segments = |caches: &[u64], end: u64| -> Vec<(u64, u64)> {
+        [0].into_iter()
+            .chain(caches.iter().copied())
+            .chain([end])
+            .tuple_windows()
+            .collect()
+    };
+    let xs = segments(xcaches, width);
+    let ys = segments(ycaches, height);
+    let zs = segments(zcaches, depth);
+
+    let cost_cuboids: u64 = iproduct!(xs, ys, zs)
+        .map(|((x0, x1), (y0, y1), (z0, z1))| cost_cuboid(x0, x1, y0, y1, z0, z1))
+        .sum();
+
+    cost_cuboids
+        + xcaches.last().unwrap_or(&0)
+        + ycaches.last().unwrap_or(&0)
+        + zcaches.last().unwrap_or(&0)
+}
+
+/// Cost of the nodes in `[x0, x1)×[y0, y1)×[z0, z1)` when they are served by
+/// the cache closest to them towards the producer.
+fn cost_cuboid(x0: u64, x1: u64, y0: u64, y1: u64, z0: u64, z1: u64) -> u64 {
+    let (w, h, d) = (x1 - x0, y1 - y0, z1 - z0);
+    let served_by = x0.max(y0).max(z0);
+
+    h * d * sum_range(x0, x1) + w * d * sum_range(y0, y1) + w * h * sum_range(z0, z1)
+        - w * h * d * served_by
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cost::get_grid_cost;
+
+    use super::get_cuboid_cost;
+
+    #[test]
+    fn flat_cuboid_is_a_grid() {
+        assert_eq!(
+            get_cuboid_cost(100, 40, 1, &[32, 54, 77], &[20], &[]),
+            get_grid_cost(100, 40, &[32, 54, 77], &[20])
+        );
+        assert_eq!(
+            get_cuboid_cost(80, 100, 1, &[29, 58], &[44, 74], &[]),
+            350224
+        );
+    }
+
+    #[test]
+    fn cost_is_symmetric() {
+        let cost = get_cuboid_cost(30, 20, 10, &[10, 20], &[7], &[4]);
+
+        assert_eq!(cost, get_cuboid_cost(20, 10, 30, &[7], &[4], &[10, 20]));
+        assert_eq!(cost, get_cuboid_cost(10, 30, 20, &[4], &[10, 20], &[7]));
+    }
+
+    #[test]
+    fn small_cuboid_by_hand() {
+        // Nodes at (x, y, z) in 2×2×2 with a single cache at x = 1
+        let by_hand: u64 = [0, 1, 1, 2, 0, 1, 1, 2].iter().sum::<u64>() + 1;
+
+        assert_eq!(get_cuboid_cost(2, 2, 2, &[1], &[], &[]), by_hand);
+    }
+}
@@ -0,0 +1,100 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+/*
+ *
+ * Copyright (c) 2022 Universidade de Vigo
+ *
+ * This program is free software; you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License version 2 as
+ * published by the Free Software Foundation;
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program; if not, write to the Free Software
+ * Foundation, Inc., 59 Temple Place, Suite 330, Boston, MA  02111-1307  USA
+ *
+ * Author: Miguel Rodríguez Pérez <miguel@det.uvigo.gal>
+ *
+ */
+
+use itertools::Itertools;
+
+use super::get_grid_cost;
+
+/// Computes the worst cost of a placement when up to `failures` of its caches
+/// go offline.
+///
+/// Every subset of at most `failures` caches is removed in turn, so the cost
+/// grows combinatorially with `failures`. Removing caches does not always
+/// increase the cost, as the farthest caches also contribute to it, so all the
+/// subset sizes are considered and not only the largest one.
+pub fn get_worst_case_cost(
+    width: u64,
+    height: u64,
+    horiz: &[u64],
+    vert: &[u64],
+    failures: usize,
+) -> u64 {
+    let ncaches = horiz.len() + vert.len();
+
+    (0..=failures.min(ncaches))
+        .flat_map(|nfailed| (0..ncaches).combinations(nfailed))
+        .map(|failed| {
+            let alive = |offset: usize, caches: &[u64]| -> Vec<u64> {
+                caches
+                    .iter()
+                    .enumerate()
+                    .filter(|(i, _)| !failed.contains(&(offset + i)))
+                    .map(|(_, &pos)| pos)
+                    .collect()
+            };
+
+            get_grid_cost(width, height, &alive(0, horiz), &alive(horiz.len(), vert))
+        })
+        .max()
+        .expect("the placement without failures is always evaluated")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cost::get_grid_cost;
+
+    use super::get_worst_case_cost;
+
+    #[test]
+    fn no_failures_is_nominal_cost() {
+        assert_eq!(
+            get_worst_case_cost(100, 40, &[32, 54, 77], &[20], 0),
+            114617
+        );
+    }
+
+    #[test]
+    fn single_cache_failure() {
+        assert_eq!(
+            get_worst_case_cost(100, 100, &[50], &[], 1),
+            get_grid_cost(100, 100, &[], &[])
+        );
+    }
+
+    #[test]
+    fn worst_failure_is_chosen() {
+        let worst = get_worst_case_cost(100, 80, &[44, 74], &[29, 58], 1);
+
+        assert_eq!(
+            worst,
+            [
+                get_grid_cost(100, 80, &[74], &[29, 58]),
+                get_grid_cost(100, 80, &[44], &[29, 58]),
+                get_grid_cost(100, 80, &[44, 74], &[58]),
+                get_grid_cost(100, 80, &[44, 74], &[29]),
+            ]
+            .into_iter()
+            .max()
+            .unwrap()
+        );
+    }
+}
@@ -20,30 +20,39 @@
  *
  */
 
+mod bound;
+mod cuboid;
+mod failures;
 mod last_repeater;
+mod model;
 
 use itertools::Itertools;
 
+use crate::Integer;
+
 use self::last_repeater::LastRepeatIter;
 
-fn cost_sq(start: u64, height: u64, end: u64) -> u64 {
+pub use self::bound::{get_budget_lower_bound, get_lower_bound};
+pub use self::cuboid::get_cuboid_cost;
+pub use self::failures::get_worst_case_cost;
+pub use self::model::{Axis, CostModel, HopCost, Transposed};
+
+fn cost_sq<N: Integer>(start: N, height: N, end: N) -> N {
     let w = end - start;
     let h = height;
 
-    if w > 0 {
-        h * w * (h + w - 2) / 2
+    if w > N::ZERO {
+        h * w * (h + w - N::TWO) / N::TWO
     } else {
-        0
+        N::ZERO
     }
 }
 
-pub fn get_cost(w: &[u64], h: &[u64]) -> u64 {
-    assert!(
-        w.len() >= h.len(),
-        "Length of w vector ({}) has to be at least the size of vector l({})",
-        w.len(),
-        h.len()
-    );
+pub fn get_cost<N: Integer>(w: &[N], h: &[N]) -> N {
+    // The cost does not change when the grid is transposed
+    if w.len() < h.len() {
+        return get_cost(h, w);
+    }
 
     let cost_w = calc_cache_costs(w);
     let cost_h = calc_cache_costs(h);
@@ -51,56 +60,126 @@ pub fn get_cost(w: &[u64], h: &[u64]) -> u64 {
     cost_w + cost_h + cost_full(create_cache_vector(w, h))
 }
 
-pub fn cost_full<'a, I>(caches: I) -> u64
+/// Computes the cost of a `width`×`height` quadrant for arbitrary sets of
+/// in-axis caches.
+///
+/// Unlike [`get_cost`], the caches in `horiz` and `vert` do not need to
+/// interleave, so this can evaluate any placement, including those where some
+/// caches have been removed. Both slices must be sorted and must not include the
+/// grid borders.
+pub fn get_grid_cost<N: Integer>(width: N, height: N, horiz: &[N], vert: &[N]) -> N {
+    let xs: Vec<_> = [N::ZERO]
+        .into_iter()
+        .chain(horiz.iter().copied())
+        .chain([width])
+        .collect();
+    let ys: Vec<_> = [N::ZERO]
+        .into_iter()
+        .chain(vert.iter().copied())
+        .chain([height])
+        .collect();
+
+    let cost_rectangles: N = xs
+        .iter()
+        .tuple_windows()
+        .cartesian_product(ys.iter().tuple_windows().collect::<Vec<_>>())
+        .map(|((&x0, &x1), (&y0, &y1))| cost_rect(x0, x1, y0, y1))
+        .sum();
+
+    cost_rectangles
+        + horiz.last().copied().unwrap_or(N::ZERO)
+        + vert.last().copied().unwrap_or(N::ZERO)
+}
+
+/// Whether the caches of every axis are strictly increasing and inside the
+/// `width`×`height` quadrant, as [`get_grid_cost`] requires.
+pub fn is_valid_placement<N: Integer>(width: N, height: N, horiz: &[N], vert: &[N]) -> bool {
+    let valid = |caches: &[N], end: N| {
+        caches.first().is_none_or(|&first| first > N::ZERO)
+            && caches.windows(2).all(|pair| pair[0] < pair[1])
+            && caches.last().is_none_or(|&last| last < end)
+    };
+
+    valid(horiz, width) && valid(vert, height)
+}
+
+/// Cost of the nodes in `[x0, x1)×[y0, y1)` when they are served by the cache
+/// closest to them towards the producer.
+fn cost_rect<N: Integer>(x0: N, x1: N, y0: N, y1: N) -> N {
+    let w = x1 - x0;
+    let h = y1 - y0;
+    let served_by = x0.max(y0);
+
+    h * sum_range(x0, x1) + w * sum_range(y0, y1) - w * h * served_by
+}
+
+fn sum_range<N: Integer>(start: N, end: N) -> N {
+    if end > start {
+        (end - start) * (start + end - N::ONE) / N::TWO
+    } else {
+        N::ZERO
+    }
+}
+
+pub fn cost_full<N, I>(caches: I) -> N
 where
-    I: IntoIterator<Item = &'a u64>,
+    N: Integer,
+    I: IntoIterator<Item = N>,
 {
     caches
         .into_iter()
         .tuple_windows()
-        .map(|(&w0, &h, &w1)| cost_sq(w0, h, w1))
+        .map(|(w0, h, w1)| cost_sq(w0, h, w1))
         .sum()
 }
 
-pub fn create_cache_vector<'a>(w: &'a [u64], h: &'a [u64]) -> impl Iterator<Item = &'a u64> + 'a {
+pub fn create_cache_vector<'a, N: Integer>(w: &'a [N], h: &'a [N]) -> impl Iterator<Item = N> + 'a {
     let iter: Box<dyn Iterator<Item = _>> = if w[0] < h[0] {
-        Box::new(create_cache_vector_ans(w, h))
+        Box::new(create_cache_vector_ans(
+            w.iter().copied(),
+            h.iter().copied(),
+        ))
     } else {
-        Box::new(create_cache_vector_rev(w, h))
+        Box::new(create_cache_vector_rev(
+            w.iter().copied(),
+            h.iter().copied(),
+        ))
     };
 
     iter
 }
 
-fn create_cache_vector_ans<'a, I1, I2>(w: I1, h: I2) -> impl Iterator<Item = &'a u64> + 'a
+fn create_cache_vector_ans<'a, N, I1, I2>(w: I1, h: I2) -> impl Iterator<Item = N> + 'a
 where
-    I1: IntoIterator<Item = &'a u64> + 'a,
-    I2: IntoIterator<Item = &'a u64> + 'a,
+    N: Integer,
+    I1: IntoIterator<Item = N> + 'a,
+    I2: IntoIterator<Item = N> + 'a,
 {
-    [&0].into_iter().chain(
+    [N::ZERO].into_iter().chain(
         LastRepeatIter::new(h)
             .zip(w)
             .flat_map(|(h1, w1)| [w1, h1].into_iter()),
     )
 }
 
-fn create_cache_vector_rev<'a, I1, I2>(w: I1, h: I2) -> impl Iterator<Item = &'a u64> + 'a
+fn create_cache_vector_rev<'a, N, I1, I2>(w: I1, h: I2) -> impl Iterator<Item = N> + 'a
 where
-    I1: IntoIterator<Item = &'a u64> + 'a,
-    I2: IntoIterator<Item = &'a u64> + 'a,
+    N: Integer,
+    I1: IntoIterator<Item = N> + 'a,
+    I2: IntoIterator<Item = N> + 'a,
 {
-    [&0].into_iter().chain(
+    [N::ZERO].into_iter().chain(
         LastRepeatIter::new(h)
             .zip(w)
             .flat_map(|(h1, w1)| [h1, w1].into_iter()),
     )
 }
 
-fn calc_cache_costs(axe_caches: &[u64]) -> u64 {
+fn calc_cache_costs<N: Integer>(axe_caches: &[N]) -> N {
     if let [.., penultimate, _last] = axe_caches {
         *penultimate
     } else {
-        0
+        N::ZERO
     }
 }
 
@@ -108,30 +187,32 @@ fn calc_cache_costs(axe_caches: &[u64]) -> u64 {
 mod tests {
     use crate::get_cost;
 
+    use super::get_grid_cost;
+
     #[test]
     fn square_100_100_3() {
-        let w = [36, 73, 100];
+        let w = [36u64, 73, 100];
         let h = [59, 100];
 
         assert_eq!(get_cost(&w, &h), 537857);
     }
     #[test]
     fn grid_100_80_4() {
-        let h = [44, 74, 100];
+        let h = [44u64, 74, 100];
         let w = [29, 58, 80];
 
         assert_eq!(get_cost(&w, &h), 350224);
     }
     #[test]
     fn grid_100_40_3() {
-        let w = [25, 50, 75, 100];
+        let w = [25u64, 50, 75, 100];
         let h = [40];
 
         assert_eq!(get_cost(&w, &h), 126075);
     }
     #[test]
     fn grid_100_40_4() {
-        let w = [32, 54, 77, 100];
+        let w = [32u64, 54, 77, 100];
         let h = [20, 40];
 
         assert_eq!(get_cost(&w, &h), 114617);
@@ -139,9 +220,36 @@ mod tests {
 
     #[test]
     fn grid_100_100_1() {
-        let w = [50, 100];
+        let w = [50u64, 100];
         let h = [100];
 
         assert_eq!(get_cost(&w, &h), 740_050);
     }
+
+    #[test]
+    fn transposed_grid_100_40_4() {
+        assert_eq!(get_cost(&[20u64, 40], &[32, 54, 77, 100]), 114617);
+    }
+
+    #[test]
+    fn grid_cost_matches_staircase() {
+        assert_eq!(get_grid_cost(100u64, 100, &[36, 73], &[59]), 537857);
+        assert_eq!(get_grid_cost(80u64, 100, &[29, 58], &[44, 74]), 350224);
+        assert_eq!(get_grid_cost(100u64, 40, &[25, 50, 75], &[]), 126075);
+        assert_eq!(get_grid_cost(100u64, 40, &[32, 54, 77], &[20]), 114617);
+        assert_eq!(get_grid_cost(100u64, 100, &[50], &[]), 740_050);
+    }
+
+    #[test]
+    fn huge_grid_in_u128() {
+        let side = 1u128 << 24;
+        let w = [side / 2, side];
+        let h = [side];
+
+        assert!(get_cost(&w, &h) > u64::MAX.into());
+        assert_eq!(
+            get_cost(&w, &h),
+            get_grid_cost(side, side, &[side / 2], &[])
+        );
+    }
 }
@@ -0,0 +1,179 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+/*
+ *
+ * Copyright (c) 2022 Universidade de Vigo
+ *
+ * This program is free software; you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License version 2 as
+ * published by the Free Software Foundation;
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program; if not, write to the Free Software
+ * Foundation, Inc., 59 Temple Place, Suite 330, Boston, MA  02111-1307  USA
+ *
+ * Author: Miguel Rodríguez Pérez <miguel@det.uvigo.gal>
+ *
+ */
+
+use crate::Integer;
+
+use super::cost_sq;
+
+/// Axis of a grid quadrant.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Axis {
+    Horizontal,
+    Vertical,
+}
+
+impl Axis {
+    /// The other axis.
+    pub fn other(self) -> Self {
+        match self {
+            Axis::Horizontal => Axis::Vertical,
+            Axis::Vertical => Axis::Horizontal,
+        }
+    }
+}
+
+/// Cost of serving the nodes of a grid quadrant through the caches placed in
+/// its axes.
+///
+/// The searchers lay the caches of both axes out as a staircase, alternating
+/// the position of a cache in one axis and in the other, that starts at the
+/// producer and ends at the grid borders. Every window of three consecutive
+/// positions `(start, height, end)` of the staircase is a block of nodes that
+/// are served by the same cache, and the blocks alternate their axis, so a
+/// model only needs to provide the cost of such a block.
+pub trait CostModel<N: Integer>: Sync {
+    /// Cost of the nodes from `start` to `end` along `axis` and from the
+    /// producer to `height` along the other axis, all of them served by the
+    /// cache at `start` of `axis`. All the positions are absolute, so models
+    /// may depend on where the nodes are.
+    fn block(&self, axis: Axis, start: N, height: N, end: N) -> N;
+
+    /// Whether the cost does not change when the grid is transposed. Searches
+    /// skip the layouts that mirror others for symmetric models.
+    fn is_symmetric(&self) -> bool {
+        false
+    }
+
+    /// Cost of all the nodes served through `staircase`, whose first block
+    /// lies along `first`.
+    fn full(&self, staircase: &[N], first: Axis) -> N {
+        staircase
+            .windows(3)
+            .zip([first, first.other()].into_iter().cycle())
+            .map(|(w, axis)| self.block(axis, w[0], w[1], w[2]))
+            .sum()
+    }
+
+    /// Part of the cost of `staircase`, whose first block lies along `first`,
+    /// that depends on the position at `index`. Moving that position changes
+    /// [`full`](Self::full) by the same amount as this, so moves can be
+    /// compared without a full evaluation.
+    fn local(&self, staircase: &[N], first: Axis, index: usize) -> N {
+        let start = index.saturating_sub(2);
+        let last = index.min(staircase.len().saturating_sub(3));
+        let axis = if start.is_multiple_of(2) {
+            first
+        } else {
+            first.other()
+        };
+
+        staircase
+            .get(start..last + 3)
+            .map_or(N::ZERO, |windows| self.full(windows, axis))
+    }
+}
+
+/// Number of hops between every node and the cache that serves it, the model
+/// of [`get_cost`](crate::get_cost).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct HopCost;
+
+impl<N: Integer> CostModel<N> for HopCost {
+    fn block(&self, _axis: Axis, start: N, height: N, end: N) -> N {
+        cost_sq(start, height, end)
+    }
+
+    fn is_symmetric(&self) -> bool {
+        true
+    }
+}
+
+/// Model of the transposed grid: the blocks along one axis cost what the
+/// blocks along the other axis cost in the wrapped model.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Transposed<M>(pub M);
+
+impl<N: Integer, M: CostModel<N>> CostModel<N> for Transposed<M> {
+    fn block(&self, axis: Axis, start: N, height: N, end: N) -> N {
+        self.0.block(axis.other(), start, height, end)
+    }
+
+    fn is_symmetric(&self) -> bool {
+        self.0.is_symmetric()
+    }
+}
+
+impl<N, M> CostModel<N> for &M
+where
+    N: Integer,
+    M: CostModel<N> + ?Sized,
+{
+    fn block(&self, axis: Axis, start: N, height: N, end: N) -> N {
+        (**self).block(axis, start, height, end)
+    }
+
+    fn is_symmetric(&self) -> bool {
+        (**self).is_symmetric()
+    }
+
+    fn full(&self, staircase: &[N], first: Axis) -> N {
+        (**self).full(staircase, first)
+    }
+
+    fn local(&self, staircase: &[N], first: Axis, index: usize) -> N {
+        (**self).local(staircase, first, index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cost::cost_full;
+
+    use super::{Axis, CostModel, HopCost};
+
+    #[test]
+    fn hops_match_cost_full() {
+        let staircase = [0u64, 34, 25, 56, 50, 78, 50, 100, 50];
+
+        assert_eq!(
+            HopCost.full(&staircase, Axis::Vertical),
+            cost_full(staircase.iter().copied())
+        );
+    }
+
+    #[test]
+    fn local_changes_as_full() {
+        let before = [0u64, 34, 25, 56, 50, 78, 50, 100, 50];
+        for index in [1, 2, 3, 5] {
+            let mut after = before;
+            after[index] -= 1;
+
+            assert_eq!(
+                HopCost.full(&before, Axis::Vertical)
+                    + HopCost.local(&after, Axis::Vertical, index),
+                HopCost.full(&after, Axis::Vertical)
+                    + HopCost.local(&before, Axis::Vertical, index)
+            );
+        }
+    }
+}
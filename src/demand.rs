@@ -0,0 +1,344 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+/*
+ *
+ * Copyright (c) 2022 Universidade de Vigo
+ *
+ * This program is free software; you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License version 2 as
+ * published by the Free Software Foundation;
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program; if not, write to the Free Software
+ * Foundation, Inc., 59 Temple Place, Suite 330, Boston, MA  02111-1307  USA
+ *
+ * Author: Miguel Rodríguez Pérez <miguel@det.uvigo.gal>
+ *
+ */
+
+//! Non-uniform traffic demand.
+//!
+//! The hop cost of [`get_cost`](crate::get_cost) assumes that every node of
+//! the grid generates the same traffic. A [`Demand`] gives every node an
+//! integer weight instead, and [`DemandModel`] generates synthetic demands to
+//! study placements without external data.
+
+use std::{error::Error, fmt::Display, str::FromStr};
+
+use crate::{
+    cost::{Axis, CostModel},
+    rng::Rng,
+};
+
+/// Weight of the busiest nodes of the generated demands. Uniform demands have
+/// a weight of one, so their cost is the hop cost.
+pub const LEVELS: u64 = 100;
+
+/// Traffic generated by every node of a quadrant.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Demand {
+    width: u64,
+    height: u64,
+    weights: Box<[u64]>,
+    /// Prefix sums of the weight, the weight times x and the weight times y,
+    /// with a row and a column of zeros before the grid.
+    sums: Box<[[u64; 3]]>,
+}
+
+impl Demand {
+    /// Creates a demand from the weights of the nodes, row by row, starting
+    /// at the producer. Returns `None` if there is not a weight for every
+    /// node of the `width`×`height` grid.
+    pub fn from_weights(width: u64, height: u64, weights: Vec<u64>) -> Option<Self> {
+        if weights.len() as u64 != width * height {
+            return None;
+        }
+
+        let stride = width as usize + 1;
+        let mut sums = vec![[0; 3]; stride * (height as usize + 1)];
+        for y in 0..height as usize {
+            for x in 0..width as usize {
+                let w = weights[y * width as usize + x];
+                let (up, left, diagonal) = (
+                    sums[y * stride + x + 1],
+                    sums[(y + 1) * stride + x],
+                    sums[y * stride + x],
+                );
+                let node = [w, w * x as u64, w * y as u64];
+                sums[(y + 1) * stride + x + 1] =
+                    [0, 1, 2].map(|i| node[i] + up[i] + left[i] - diagonal[i]);
+            }
+        }
+
+        Some(Self {
+            width,
+            height,
+            weights: weights.into_boxed_slice(),
+            sums: sums.into_boxed_slice(),
+        })
+    }
+
+    /// Creates a demand with the weight `weight(x, y)` for every node.
+    pub fn from_fn<F: Fn(u64, u64) -> u64>(width: u64, height: u64, weight: F) -> Self {
+        let weights = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .map(|(x, y)| weight(x, y))
+            .collect();
+
+        Self::from_weights(width, height, weights).expect("There is a weight for every node")
+    }
+
+    /// Creates a demand where every node generates the same traffic.
+    pub fn uniform(width: u64, height: u64) -> Self {
+        Self::from_fn(width, height, |_, _| 1)
+    }
+
+    /// Get the demand's width.
+    pub fn width(&self) -> u64 {
+        self.width
+    }
+
+    /// Get the demand's height.
+    pub fn height(&self) -> u64 {
+        self.height
+    }
+
+    /// Weight of the node at `(x, y)`.
+    pub fn weight(&self, x: u64, y: u64) -> u64 {
+        self.weights[(y * self.width + x) as usize]
+    }
+
+    /// Same demand with the axes swapped.
+    pub fn transposed(&self) -> Self {
+        Self::from_fn(self.height, self.width, |x, y| self.weight(y, x))
+    }
+
+    /// Sums of the weight, the weight times x and the weight times y of the
+    /// nodes in `[x0, x1)×[y0, y1)`.
+    fn rect_sums(&self, x0: u64, x1: u64, y0: u64, y1: u64) -> [u64; 3] {
+        let stride = self.width + 1;
+        let at = |x: u64, y: u64| self.sums[(y * stride + x) as usize];
+        let (outer, inner) = (at(x1, y1), at(x0, y0));
+        let (top, left) = (at(x1, y0), at(x0, y1));
+
+        [0, 1, 2].map(|i| outer[i] + inner[i] - top[i] - left[i])
+    }
+
+    /// Computes the cost of a placement as [`get_grid_cost`] does, but
+    /// weighting the hops of every node by its demand.
+    ///
+    /// [`get_grid_cost`]: crate::cost::get_grid_cost
+    pub fn cost(&self, horiz: &[u64], vert: &[u64]) -> u64 {
+        self.served_cost(horiz, vert)
+            + horiz.last().copied().unwrap_or(0)
+            + vert.last().copied().unwrap_or(0)
+    }
+
+    /// Weighted hops of the nodes, without the contribution of the caches.
+    fn served_cost(&self, horiz: &[u64], vert: &[u64]) -> u64 {
+        let xs: Vec<_> = [0]
+            .into_iter()
+            .chain(horiz.iter().copied())
+            .chain([self.width])
+            .collect();
+        let ys: Vec<_> = [0]
+            .into_iter()
+            .chain(vert.iter().copied())
+            .chain([self.height])
+            .collect();
+
+        xs.windows(2)
+            .flat_map(|x| ys.windows(2).map(move |y| (x[0], x[1], y[0], y[1])))
+            .map(|(x0, x1, y0, y1)| {
+                let [weight, weighted_x, weighted_y] = self.rect_sums(x0, x1, y0, y1);
+                weighted_x + weighted_y - weight * x0.max(y0)
+            })
+            .sum()
+    }
+}
+
+/// Hops weighted by the demand of every node. Searches with this model add
+/// the same distances to the caches as [`Demand::cost`], so their cost is the
+/// weighted cost.
+impl CostModel<u64> for Demand {
+    fn block(&self, axis: Axis, start: u64, height: u64, end: u64) -> u64 {
+        let [weight, weighted_x, weighted_y] = match axis {
+            Axis::Horizontal => self.rect_sums(start, end, 0, height),
+            Axis::Vertical => self.rect_sums(0, height, start, end),
+        };
+
+        weighted_x + weighted_y - weight * start
+    }
+}
+
+/// Generators of synthetic demands. Weights range from zero to [`LEVELS`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DemandModel {
+    /// Every node generates the same traffic.
+    Uniform,
+    /// Gaussian hotspot centred at the node `(x, y)`, with a standard
+    /// deviation of `sigma` hops.
+    Hotspot { x: f64, y: f64, sigma: f64 },
+    /// Dense bands parallel to the horizontal axis, like the population along
+    /// the latitudes.
+    Bands { bands: u64 },
+    /// Independent random weights for every node.
+    Random { seed: u64 },
+}
+
+impl DemandModel {
+    /// Generates the demand of a `width`×`height` quadrant.
+    pub fn generate(&self, width: u64, height: u64) -> Demand {
+        let level = |share: f64| (share * LEVELS as f64).round() as u64;
+
+        match *self {
+            DemandModel::Uniform => Demand::uniform(width, height),
+            DemandModel::Hotspot { x, y, sigma } => Demand::from_fn(width, height, |i, j| {
+                let distance = (i as f64 - x).powi(2) + (j as f64 - y).powi(2);
+                level((-distance / (2.0 * sigma * sigma)).exp())
+            }),
+            DemandModel::Bands { bands } => Demand::from_fn(width, height, |_, j| {
+                let phase = std::f64::consts::PI * bands as f64 * (j as f64 + 0.5) / height as f64;
+                level(phase.sin().powi(2))
+            }),
+            DemandModel::Random { seed } => {
+                let mut rng = Rng(seed);
+                let weights = (0..width * height).map(|_| rng.below(LEVELS + 1)).collect();
+                Demand::from_weights(width, height, weights)
+                    .expect("There is a weight for every node")
+            }
+        }
+    }
+}
+
+impl Display for DemandModel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DemandModel::Uniform => write!(f, "uniform"),
+            DemandModel::Hotspot { x, y, sigma } => write!(f, "hotspot:{x},{y},{sigma}"),
+            DemandModel::Bands { bands } => write!(f, "bands:{bands}"),
+            DemandModel::Random { seed } => write!(f, "random:{seed}"),
+        }
+    }
+}
+
+/// Error returned when a demand model cannot be parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseDemandModelError(String);
+
+impl Display for ParseDemandModelError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Invalid demand model '{}'. Use uniform, hotspot:x,y,sigma, bands:count or random:seed",
+            self.0
+        )
+    }
+}
+
+impl Error for ParseDemandModelError {}
+
+impl FromStr for DemandModel {
+    type Err = ParseDemandModelError;
+
+    /// Parses models written as `uniform`, `hotspot:x,y,sigma`, `bands:count`
+    /// or `random:seed`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = || ParseDemandModelError(s.to_owned());
+        let (name, params) = s.split_once(':').unwrap_or((s, ""));
+
+        match (name, params) {
+            ("uniform", "") => Ok(DemandModel::Uniform),
+            ("hotspot", params) => {
+                let values: Vec<f64> = params
+                    .split(',')
+                    .map(str::parse)
+                    .collect::<Result<_, _>>()
+                    .map_err(|_| error())?;
+                match values[..] {
+                    [x, y, sigma] if sigma > 0.0 => Ok(DemandModel::Hotspot { x, y, sigma }),
+                    _ => Err(error()),
+                }
+            }
+            ("bands", params) => match params.parse() {
+                Ok(bands) if bands > 0 => Ok(DemandModel::Bands { bands }),
+                _ => Err(error()),
+            },
+            ("random", params) => params
+                .parse()
+                .map(|seed| DemandModel::Random { seed })
+                .map_err(|_| error()),
+            _ => Err(error()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{cost::get_grid_cost, get_cost};
+
+    use super::{Demand, DemandModel, LEVELS};
+
+    #[test]
+    fn uniform_is_hop_cost() {
+        let demand = Demand::uniform(100, 80);
+
+        assert_eq!(
+            demand.cost(&[29, 58], &[44, 74]),
+            get_cost(&[29u64, 58, 100], &[44, 74, 80])
+        );
+        assert_eq!(
+            demand.cost(&[25, 75], &[]),
+            get_grid_cost(100u64, 80, &[25, 75], &[])
+        );
+    }
+
+    #[test]
+    fn weights_scale_the_cost() {
+        let single = DemandModel::Hotspot {
+            x: 30.0,
+            y: 20.0,
+            sigma: 5.0,
+        }
+        .generate(60, 40);
+        let double = Demand::from_fn(60, 40, |x, y| 2 * single.weight(x, y));
+        let (horiz, vert) = ([20, 40], [10, 30]);
+
+        assert_eq!(single.weight(30, 20), LEVELS);
+        assert_eq!(
+            double.cost(&horiz, &vert) - 70,
+            2 * (single.cost(&horiz, &vert) - 70)
+        );
+    }
+
+    #[test]
+    fn transposed_cost() {
+        let demand = DemandModel::Random { seed: 7 }.generate(30, 20);
+
+        assert_eq!(
+            demand.cost(&[10, 20], &[5]),
+            demand.transposed().cost(&[5], &[10, 20])
+        );
+    }
+
+    #[test]
+    fn parse_models() {
+        for model in ["uniform", "hotspot:10,20.5,3", "bands:3", "random:42"] {
+            assert_eq!(model.parse::<DemandModel>().unwrap().to_string(), model);
+        }
+        for model in [
+            "hotspot:1,2",
+            "hotspot:1,2,0",
+            "bands:0",
+            "random:",
+            "gaussian",
+        ] {
+            assert!(model.parse::<DemandModel>().is_err());
+        }
+    }
+}
@@ -0,0 +1,203 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+/*
+ *
+ * Copyright (c) 2022 Universidade de Vigo
+ *
+ * This program is free software; you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License version 2 as
+ * published by the Free Software Foundation;
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program; if not, write to the Free Software
+ * Foundation, Inc., 59 Temple Place, Suite 330, Boston, MA  02111-1307  USA
+ *
+ * Author: Miguel Rodríguez Pérez <miguel@det.uvigo.gal>
+ *
+ */
+
+//! Export of placements to the configuration of NDN emulations.
+//!
+//! Every node of the `width`×`height` quadrant is named after its coordinates,
+//! with the producer at `n0_0`. Caches get a Content Store of
+//! [`ExportParams::cs_size`] packets, and the rest of the nodes do not cache.
+//! The quadrant can be written as an ndnSIM annotated topology, or as a
+//! fragment of the NFD configuration of every node.
+
+use std::io::{self, Write};
+
+use crate::Solution;
+
+/// Settings of the exported network.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ExportParams {
+    /// Content Store size of the caches, in packets.
+    pub cs_size: u64,
+    /// Bandwidth of the links, in ndnSIM notation.
+    pub bandwidth: String,
+    /// Delay of the links, in ndnSIM notation.
+    pub delay: String,
+    /// Queue length of the links, in packets.
+    pub queue: u64,
+}
+
+impl Default for ExportParams {
+    fn default() -> Self {
+        Self {
+            cs_size: 1000,
+            bandwidth: "10Mbps".to_owned(),
+            delay: "10ms".to_owned(),
+            queue: 20,
+        }
+    }
+}
+
+/// Name of the node at `(x, y)`.
+pub fn node_name(x: u64, y: u64) -> String {
+    format!("n{x}_{y}")
+}
+
+/// Content Store size of the node at `(x, y)`, in packets.
+pub fn cs_size(solution: &Solution<u64>, params: &ExportParams, x: u64, y: u64) -> u64 {
+    let is_cache = (y == 0 && solution.horizontal_caches().contains(&x))
+        || (x == 0 && solution.vertical_caches().contains(&y));
+
+    if is_cache {
+        params.cs_size
+    } else {
+        0
+    }
+}
+
+fn nodes(width: u64, height: u64) -> impl Iterator<Item = (u64, u64)> {
+    (0..height).flat_map(move |y| (0..width).map(move |x| (x, y)))
+}
+
+/// Writes the `width`×`height` quadrant of `solution` in the annotated
+/// topology format of ndnSIM. The comment column holds the role of every
+/// node, with the Content Store size of the caches as `cs=<packets>`.
+pub fn write_ndnsim_topology<W: Write>(
+    writer: &mut W,
+    width: u64,
+    height: u64,
+    solution: &Solution<u64>,
+    params: &ExportParams,
+) -> io::Result<()> {
+    writeln!(
+        writer,
+        "# {width}x{height} grid quadrant with the producer at n0_0"
+    )?;
+    writeln!(writer, "router")?;
+    writeln!(writer)?;
+    writeln!(writer, "# node comment yPos xPos")?;
+    for (x, y) in nodes(width, height) {
+        let comment = match cs_size(solution, params, x, y) {
+            _ if (x, y) == (0, 0) => "producer".to_owned(),
+            0 => "NA".to_owned(),
+            size => format!("cs={size}"),
+        };
+        writeln!(writer, "{} {comment} {y} {x}", node_name(x, y))?;
+    }
+
+    writeln!(writer)?;
+    writeln!(writer, "link")?;
+    writeln!(writer)?;
+    writeln!(writer, "# srcNode dstNode bandwidth metric delay queue")?;
+    for (x, y) in nodes(width, height) {
+        let neighbours = [(x + 1, y), (x, y + 1)];
+        for (nx, ny) in neighbours
+            .into_iter()
+            .filter(|&(nx, ny)| nx < width && ny < height)
+        {
+            writeln!(
+                writer,
+                "{} {} {} 1 {} {}",
+                node_name(x, y),
+                node_name(nx, ny),
+                params.bandwidth,
+                params.delay,
+                params.queue
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Fragment of the NFD configuration of every node of the `width`×`height`
+/// quadrant of `solution`, with the name of the node. Only the Content Store
+/// capacity is set, so nodes that are not caches have a capacity of zero.
+pub fn nfd_fragments<'a>(
+    width: u64,
+    height: u64,
+    solution: &'a Solution<u64>,
+    params: &'a ExportParams,
+) -> impl Iterator<Item = (String, String)> + 'a {
+    nodes(width, height).map(move |(x, y)| {
+        let name = node_name(x, y);
+        let fragment = format!(
+            "; Content Store of {name}\ntables\n{{\n  cs_max_packets {}\n}}\n",
+            cs_size(solution, params, x, y)
+        );
+
+        (name, fragment)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::get_cache_locations;
+
+    use super::{nfd_fragments, write_ndnsim_topology, ExportParams};
+
+    #[test]
+    fn ndnsim_topology() {
+        let sol = get_cache_locations(10u64, 6, 2, 1);
+        let mut output = Vec::new();
+        write_ndnsim_topology(&mut output, 10, 6, &sol, &ExportParams::default()).unwrap();
+        let output = String::from_utf8(output).unwrap();
+
+        let (routers, links) = output.split_once("\nlink\n").unwrap();
+        let routers: Vec<_> = routers.lines().filter(|l| l.starts_with('n')).collect();
+        let links = links.lines().filter(|l| l.starts_with('n')).count();
+
+        assert_eq!(routers.len(), 60);
+        assert_eq!(links, 9 * 6 + 10 * 5);
+        assert_eq!(routers[0], "n0_0 producer 0 0");
+        assert_eq!(routers.iter().filter(|l| l.contains("cs=1000")).count(), 3);
+        assert!(
+            routers.contains(&format!("n0_{0} cs=1000 {0} 0", sol.vertical_caches()[0]).as_str())
+        );
+    }
+
+    #[test]
+    fn nfd_capacities() {
+        let sol = get_cache_locations(10u64, 6, 2, 1);
+        let params = ExportParams {
+            cs_size: 500,
+            ..Default::default()
+        };
+        let fragments: Vec<_> = nfd_fragments(10, 6, &sol, &params).collect();
+        let caches: Vec<_> = fragments
+            .iter()
+            .filter(|(_, fragment)| fragment.contains("cs_max_packets 500"))
+            .map(|(name, _)| name.clone())
+            .collect();
+
+        assert_eq!(fragments.len(), 60);
+        let x = sol.horizontal_caches();
+        assert_eq!(
+            caches,
+            [
+                format!("n{}_0", x[0]),
+                format!("n{}_0", x[1]),
+                format!("n0_{}", sol.vertical_caches()[0])
+            ]
+        );
+    }
+}
@@ -56,6 +56,10 @@ impl<E, Idx> FilteredSlice<E, Idx> {
         self.index_translations.len()
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.index_translations.is_empty()
+    }
+
     pub fn create<I>(elements: E, valid_indices: I) -> Self
     where
         E: Index<Idx>,
@@ -71,6 +75,14 @@ impl<E, Idx> FilteredSlice<E, Idx> {
         }
     }
 
+    /// Index in the underlying elements of the element at `index`.
+    pub fn raw_index(&self, index: usize) -> Idx
+    where
+        Idx: Copy,
+    {
+        self.index_translations[index]
+    }
+
     pub fn get_raw_ref(&self) -> &E {
         &self.elements
     }
@@ -0,0 +1,352 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+/*
+ *
+ * Copyright (c) 2022 Universidade de Vigo
+ *
+ * This program is free software; you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License version 2 as
+ * published by the Free Software Foundation;
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program; if not, write to the Free Software
+ * Foundation, Inc., 59 Temple Place, Suite 330, Boston, MA  02111-1307  USA
+ *
+ * Author: Miguel Rodríguez Pérez <miguel@det.uvigo.gal>
+ *
+ */
+
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+
+use crate::{cost::CostModel, filtered_slice::FilteredSlice, rng::Rng, Objective};
+
+use super::{
+    horizontal::SearcherFirstHorizontal, resilient::ResilientSearcher, sealed::Staircase,
+    vertical::SearcherFirstVertical, Layout, Move, Observer, Searcher, Solution,
+};
+
+/// Parameters of the simulated annealing.
+///
+/// Temperatures are relative to the objective of the initial placement, so the
+/// same parameters work for grids of any size.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AnnealParams {
+    /// Temperature of the first iteration.
+    pub initial_temperature: f64,
+    /// Temperature of the last iteration. The temperature decreases
+    /// geometrically between both.
+    pub final_temperature: f64,
+    /// Moves tried in every run.
+    pub iterations: usize,
+    /// Independent runs from the initial layout of each searcher.
+    pub restarts: usize,
+    /// Seed of the random moves. The same seed gives the same solution.
+    pub seed: u64,
+}
+
+impl Default for AnnealParams {
+    fn default() -> Self {
+        Self {
+            initial_temperature: 1e-2,
+            final_temperature: 1e-6,
+            iterations: 20_000,
+            restarts: 8,
+            seed: 0,
+        }
+    }
+}
+
+/// Wraps a searcher so that it anneals the placement before its local search,
+/// to escape from the local optima of rugged objectives.
+#[derive(Debug)]
+pub struct AnnealSearcher<S> {
+    searcher: S,
+    params: AnnealParams,
+    rng: Rng,
+}
+
+impl<S> Staircase<u64> for AnnealSearcher<S>
+where
+    S: Searcher<u64>,
+{
+    fn filtered_mut(&mut self) -> &mut FilteredSlice<Vec<u64>, usize> {
+        self.searcher.filtered_mut()
+    }
+
+    fn filtered(&self) -> &FilteredSlice<Vec<u64>, usize> {
+        self.searcher.filtered()
+    }
+}
+
+impl<S> AnnealSearcher<S>
+where
+    S: Searcher<u64>,
+{
+    /// Wraps `searcher`, picking the moves with the seed of `params`.
+    pub fn create(searcher: S, params: AnnealParams) -> Self {
+        let rng = Rng(params.seed);
+
+        Self {
+            searcher,
+            params,
+            rng,
+        }
+    }
+
+    /// Picks a random move, with steps that shrink as the temperature falls.
+    fn random_move(&mut self, scale: f64) -> (usize, Move<u64>) {
+        let len = self.filtered().len();
+        let index = self.rng.below(len as u64) as usize;
+        let max_step = (self.width() as f64 / (len + 1) as f64 * scale).max(1.0);
+        let step = 1 + self.rng.below(max_step as u64);
+
+        if self.rng.below(2) == 0 {
+            (index, Move::Forward(step))
+        } else {
+            (index, Move::Backward(step))
+        }
+    }
+
+    fn anneal(&mut self, observer: &mut dyn Observer<u64>) {
+        let len = self.filtered().len();
+        let mut current = self.objective();
+        let mut best = current;
+        let mut best_positions: Vec<_> = (0..len).map(|i| self.filtered()[i]).collect();
+
+        let initial = self.params.initial_temperature * current as f64;
+        let cooling = (self.params.final_temperature / self.params.initial_temperature)
+            .powf(1.0 / self.params.iterations.max(1) as f64);
+        let mut temperature = initial;
+
+        for _ in 0..self.params.iterations {
+            if observer.should_stop() {
+                break;
+            }
+
+            let (index, mv) = self.random_move(temperature / initial);
+            temperature *= cooling;
+            if !self.is_feasible(index, mv) {
+                continue;
+            }
+
+            let prev = self.filtered()[index];
+            self.apply_move(index, mv);
+            let cost = self.is_valid().then(|| self.objective());
+            match cost {
+                Some(cost)
+                    if cost <= current
+                        || self.rng.unit() < (-((cost - current) as f64) / temperature).exp() =>
+                {
+                    current = cost
+                }
+                _ => {
+                    self.filtered_mut()[index] = prev;
+                    continue;
+                }
+            }
+
+            if current < best {
+                best = current;
+                best_positions = (0..len).map(|i| self.filtered()[i]).collect();
+            }
+        }
+
+        self.place(&best_positions);
+    }
+}
+
+impl<S> Searcher<u64> for AnnealSearcher<S>
+where
+    S: Searcher<u64>,
+{
+    fn model(&self) -> Option<&dyn CostModel<u64>> {
+        self.searcher.model()
+    }
+
+    fn is_vertical(&self, index: usize) -> bool {
+        self.searcher.is_vertical(index)
+    }
+
+    fn layout(&self) -> Layout {
+        self.searcher.layout()
+    }
+
+    fn width(&self) -> u64 {
+        self.searcher.width()
+    }
+
+    fn height(&self) -> u64 {
+        self.searcher.height()
+    }
+
+    fn try_move(&mut self, index: usize, mv: Move<u64>) -> bool {
+        self.searcher.try_move(index, mv)
+    }
+
+    fn objective(&self) -> u64 {
+        self.searcher.objective()
+    }
+
+    fn get_cost(&self) -> u64 {
+        self.searcher.get_cost()
+    }
+
+    fn vertical_caches(&self) -> Box<dyn Iterator<Item = u64> + '_> {
+        self.searcher.vertical_caches()
+    }
+
+    fn horizontal_caches(&self) -> Box<dyn Iterator<Item = u64> + '_> {
+        self.searcher.horizontal_caches()
+    }
+
+    /// Anneals the placement and finishes with the local search of the wrapped
+    /// searcher, so the solution is a local optimum. Only the moves of the
+    /// local search reach `observer`.
+    fn find_solution_observed(&mut self, observer: &mut dyn Observer<u64>) -> Solution<u64> {
+        assert!(!self.filtered().is_empty(), "We need some caches");
+
+        self.anneal(observer);
+        self.searcher.find_solution_observed(observer)
+    }
+}
+
+/// Looks for the placement of `nhoriz` horizontal and `nvert` vertical caches
+/// that minimises `objective` with simulated annealing.
+///
+/// Every restart runs in parallel from both initial layouts, each with its own
+/// seed derived from `params.seed`, and the best solution is returned.
+pub fn get_annealed_cache_locations(
+    width: u64,
+    height: u64,
+    nhoriz: usize,
+    nvert: usize,
+    objective: Objective,
+    params: &AnnealParams,
+) -> Solution<u64> {
+    anneal_all(
+        width,
+        height,
+        nhoriz,
+        nvert,
+        objective,
+        params,
+        |searcher, _| searcher.find_solution(),
+    )
+}
+
+/// Runs `search` in parallel with the annealing searchers of every restart
+/// from both layouts, and returns the best solution.
+pub(crate) fn anneal_all<F>(
+    width: u64,
+    height: u64,
+    nhoriz: usize,
+    nvert: usize,
+    objective: Objective,
+    params: &AnnealParams,
+    search: F,
+) -> Solution<u64>
+where
+    F: Fn(&mut dyn Searcher<u64>, Layout) -> Solution<u64> + Sync,
+{
+    if nvert > nhoriz {
+        return anneal_all(height, width, nvert, nhoriz, objective, params, search).transposed();
+    }
+
+    let solutions: Vec<_> = (0..2 * params.restarts.max(1))
+        .into_par_iter()
+        .map(|run| {
+            let params = AnnealParams {
+                seed: params.seed.wrapping_add(run as u64),
+                ..params.clone()
+            };
+            let layout = if run % 2 == 0 {
+                Layout::HorizontalFirst
+            } else {
+                Layout::VerticalFirst
+            };
+
+            match (objective, layout) {
+                (Objective::Nominal, Layout::HorizontalFirst) => search(
+                    &mut AnnealSearcher::create(
+                        SearcherFirstHorizontal::create(width, height, nhoriz, nvert),
+                        params,
+                    ),
+                    layout,
+                ),
+                (Objective::Nominal, Layout::VerticalFirst) => search(
+                    &mut AnnealSearcher::create(
+                        SearcherFirstVertical::create(width, height, nhoriz, nvert),
+                        params,
+                    ),
+                    layout,
+                ),
+                (Objective::WorstCase { failures }, Layout::HorizontalFirst) => search(
+                    &mut AnnealSearcher::create(
+                        ResilientSearcher::create(
+                            SearcherFirstHorizontal::create(width, height, nhoriz, nvert),
+                            failures,
+                        ),
+                        params,
+                    ),
+                    layout,
+                ),
+                (Objective::WorstCase { failures }, Layout::VerticalFirst) => search(
+                    &mut AnnealSearcher::create(
+                        ResilientSearcher::create(
+                            SearcherFirstVertical::create(width, height, nhoriz, nvert),
+                            failures,
+                        ),
+                        params,
+                    ),
+                    layout,
+                ),
+            }
+        })
+        .collect();
+
+    solutions
+        .into_iter()
+        .min()
+        .expect("There is at least one run")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{get_cache_locations, get_cost, Objective};
+
+    use super::{get_annealed_cache_locations, AnnealParams};
+
+    #[test]
+    fn same_seed_same_solution() {
+        let params = AnnealParams {
+            restarts: 2,
+            ..Default::default()
+        };
+        let sol = get_annealed_cache_locations(100, 80, 2, 2, Objective::Nominal, &params);
+
+        assert_eq!(
+            sol,
+            get_annealed_cache_locations(100, 80, 2, 2, Objective::Nominal, &params)
+        );
+    }
+
+    #[test]
+    fn never_worse_than_local_search() {
+        let params = AnnealParams::default();
+        let sol = get_annealed_cache_locations(100, 60, 3, 1, Objective::Nominal, &params);
+
+        assert!(sol.cost() <= get_cache_locations(100, 60, 3, 1).cost());
+        assert_eq!(
+            sol.cost(),
+            get_cost(
+                &[sol.horizontal_caches(), &[100]].concat(),
+                &[sol.vertical_caches(), &[60]].concat()
+            )
+        );
+    }
+}
@@ -20,21 +20,30 @@
  *
  */
 
-use crate::filtered_slice::FilteredSlice;
+use crate::{
+    cost::{CostModel, HopCost},
+    filtered_slice::FilteredSlice,
+    Integer,
+};
 
-use super::{Problem, Searcher};
+use super::{sealed::Staircase, Layout, Problem, Searcher};
 
 #[derive(Debug)]
-pub struct SearcherFirstHorizontal {
-    problem: Problem,
+pub struct SearcherFirstHorizontal<N, M = HopCost> {
+    problem: Problem<N, M>,
 }
 
-impl SearcherFirstHorizontal {
-    pub fn create(width: u64, height: u64, nhoriz: usize, nvert: usize) -> Self
-where {
+impl<N: Integer> SearcherFirstHorizontal<N> {
+    pub fn create(width: N, height: N, nhoriz: usize, nvert: usize) -> Self {
+        Self::create_with(HopCost, width, height, nhoriz, nvert)
+    }
+}
+
+impl<N: Integer, M: CostModel<N>> SearcherFirstHorizontal<N, M> {
+    pub fn create_with(model: M, width: N, height: N, nhoriz: usize, nvert: usize) -> Self {
         assert!(nhoriz >= nvert);
-        assert!(width as usize > nhoriz);
-        assert!(height as usize > nvert);
+        assert!(width > N::from_usize(nhoriz));
+        assert!(height > N::from_usize(nvert));
 
         let caches: Vec<_> = [0]
             .into_iter() // Origin
@@ -42,10 +51,11 @@ where {
                 // While there are vertical caches
                 (0..nvert).flat_map(|x| [2 * x + 1, 2 * (x + 1)].into_iter()),
             )
-            .map(|x| x as u64)
+            .map(N::from_usize)
             .chain(
                 // This is for horizontal ones
-                (0..nhoriz - nvert).flat_map(|x| [(2 * nvert + x + 1) as u64, height].into_iter()),
+                (0..nhoriz - nvert)
+                    .flat_map(|x| [N::from_usize(2 * nvert + x + 1), height].into_iter()),
             )
             .chain([width, height])
             .collect();
@@ -61,14 +71,30 @@ where {
                 nhoriz,
                 nvert,
                 width,
+                height,
                 filtered,
+                model,
             },
         }
     }
 }
 
-impl Searcher for SearcherFirstHorizontal {
-    fn vertical_caches(&self) -> Box<dyn Iterator<Item = u64> + '_> {
+impl<N: Integer, M: CostModel<N>> Staircase<N> for SearcherFirstHorizontal<N, M> {
+    fn filtered_mut(&mut self) -> &mut FilteredSlice<Vec<N>, usize> {
+        self.problem.filtered_mut()
+    }
+
+    fn filtered(&self) -> &FilteredSlice<Vec<N>, usize> {
+        self.problem.filtered()
+    }
+}
+
+impl<N: Integer, M: CostModel<N>> Searcher<N> for SearcherFirstHorizontal<N, M> {
+    fn model(&self) -> Option<&dyn CostModel<N>> {
+        Some(&self.problem.model)
+    }
+
+    fn vertical_caches(&self) -> Box<dyn Iterator<Item = N> + '_> {
         Box::new(
             (0..self.problem.nvert)
                 .map(|x| 2 * (x + 1))
@@ -76,7 +102,7 @@ impl Searcher for SearcherFirstHorizontal {
         )
     }
 
-    fn horizontal_caches(&self) -> Box<dyn Iterator<Item = u64> + '_> {
+    fn horizontal_caches(&self) -> Box<dyn Iterator<Item = N> + '_> {
         Box::new(
             (0..self.problem.nhoriz)
                 .map(|x| 2 * x + 1)
@@ -84,16 +110,20 @@ impl Searcher for SearcherFirstHorizontal {
         )
     }
 
-    fn width(&self) -> u64 {
-        self.problem.width
+    fn is_vertical(&self, index: usize) -> bool {
+        index < 2 * self.problem.nvert && index % 2 == 1
     }
 
-    fn filtered_mut(&mut self) -> &mut FilteredSlice<Vec<u64>, usize> {
-        self.problem.filtered_mut()
+    fn layout(&self) -> Layout {
+        Layout::HorizontalFirst
     }
 
-    fn filtered(&self) -> &FilteredSlice<Vec<u64>, usize> {
-        self.problem.filtered()
+    fn width(&self) -> N {
+        self.problem.width
+    }
+
+    fn height(&self) -> N {
+        self.problem.height
     }
 }
 
@@ -103,7 +133,7 @@ mod tests {
 
     #[test]
     fn test_search_100_60_3_1() {
-        let mut searcher = SearcherFirstHorizontal::create(100, 60, 3, 1);
+        let mut searcher = SearcherFirstHorizontal::<u64>::create(100, 60, 3, 1);
         let sol = searcher.find_solution();
 
         assert_eq!(sol.horizontal_caches(), [27, 54, 77]);
@@ -113,11 +143,11 @@ mod tests {
 
     #[test]
     fn test_search_100_100_0_0() {
-        let mut searcher = SearcherFirstHorizontal::create(100, 100, 1, 0);
+        let mut searcher = SearcherFirstHorizontal::<u64>::create(100, 100, 1, 0);
         let sol = searcher.find_solution();
 
         assert_eq!(sol.horizontal_caches(), [50]);
-        assert_eq!(sol.vertical_caches(), []);
+        assert_eq!(sol.vertical_caches(), [0u64; 0]);
         assert_eq!(sol.cost(), 740_050);
     }
 }
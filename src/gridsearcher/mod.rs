@@ -20,114 +20,331 @@
  *
  */
 
+mod anneal;
 mod horizontal;
+mod resilient;
+mod steps;
 mod vertical;
+mod warm;
+mod weighted;
 
-use std::cmp::min;
+use std::{cmp::min, fmt::Display};
 
-use crate::{cost::cost_full, filtered_slice::FilteredSlice};
+use crate::{
+    cost::{get_budget_lower_bound, Axis, CostModel, HopCost, Transposed},
+    filtered_slice::FilteredSlice,
+    problem::{ProblemError, MAX_FAILURES},
+    Integer,
+};
 
-use self::{horizontal::SearcherFirstHorizontal, vertical::SearcherFirstVertical};
+use self::sealed::Staircase;
+
+pub(crate) use self::anneal::anneal_all;
+pub use self::anneal::{get_annealed_cache_locations, AnnealParams, AnnealSearcher};
+pub use self::horizontal::SearcherFirstHorizontal;
+pub use self::resilient::ResilientSearcher;
+pub use self::steps::{Observer, Step, Steps};
+pub use self::vertical::SearcherFirstVertical;
+pub use self::warm::{get_cache_locations_from, PlacementError};
+pub use self::weighted::get_weighted_cache_locations;
 
 #[derive(Debug)]
-struct Problem {
+struct Problem<N, M> {
     nhoriz: usize,
     nvert: usize,
-    width: u64,
-    filtered: FilteredSlice<Vec<u64>, usize>,
+    width: N,
+    height: N,
+    filtered: FilteredSlice<Vec<N>, usize>,
+    model: M,
 }
 
-impl Problem {
+impl<N, M> Problem<N, M> {
     /// Get a mutable reference to the problem's filtered.
-    fn filtered_mut(&mut self) -> &mut FilteredSlice<Vec<u64>, usize> {
+    fn filtered_mut(&mut self) -> &mut FilteredSlice<Vec<N>, usize> {
         &mut self.filtered
     }
 
     /// Get a reference to the problem's filtered.
-    fn filtered(&self) -> &FilteredSlice<Vec<u64>, usize> {
+    fn filtered(&self) -> &FilteredSlice<Vec<N>, usize> {
         &self.filtered
     }
 }
 
-trait Searcher {
-    fn width(&self) -> u64;
+/// Displacement of a cache along its axis.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Move<N> {
+    /// Away from the producer.
+    Forward(N),
+    /// Towards the producer.
+    Backward(N),
+}
+
+mod sealed {
+    use super::Move;
+    use crate::{filtered_slice::FilteredSlice, Integer};
 
-    /// Get a mutable reference to the problem's filtered.
-    fn filtered_mut(&mut self) -> &mut FilteredSlice<Vec<u64>, usize>;
+    /// Raw access to the staircase of a searcher. It is only reachable from
+    /// this crate, as writing to it can break the order of the caches.
+    pub trait Staircase<N: Integer> {
+        /// Get a mutable reference to the problem's filtered.
+        fn filtered_mut(&mut self) -> &mut FilteredSlice<Vec<N>, usize>;
 
-    /// Get a reference to the problem's filtered.
-    fn filtered(&self) -> &FilteredSlice<Vec<u64>, usize>;
+        /// Get a reference to the problem's filtered.
+        fn filtered(&self) -> &FilteredSlice<Vec<N>, usize>;
+
+        /// Moves the cache at `index`, without checking the move.
+        fn apply_move(&mut self, index: usize, mv: Move<N>) {
+            match mv {
+                Move::Forward(step) => self.filtered_mut()[index] += step,
+                Move::Backward(step) => self.filtered_mut()[index] -= step,
+            }
+        }
+
+        /// Replaces the caches by `positions`, given in the order of the
+        /// filtered view, without checking them.
+        fn place(&mut self, positions: &[N]) {
+            for (i, &pos) in positions.iter().enumerate() {
+                self.filtered_mut()[i] = pos;
+            }
+        }
+    }
+}
+
+const NO_MODEL: &str = "Searchers without a cost model override their objective";
+
+/// Local search over the positions of the caches of a grid quadrant.
+///
+/// The caches of both axes are kept in a staircase, alternating their axes
+/// from the producer outwards. Searchers must keep the order of the caches in
+/// the staircase, so they never cross each other.
+///
+/// The trait is sealed, so it is only implemented by the searchers of this
+/// crate. Other search strategies can still drive them with
+/// [`move_cache`](Self::move_cache), which only takes feasible moves.
+pub trait Searcher<N: Integer>: Staircase<N> {
+    /// Width of the grid quadrant.
+    fn width(&self) -> N;
+
+    /// Height of the grid quadrant.
+    fn height(&self) -> N;
+
+    /// Model of the cost minimised by the search, if that cost adds up over
+    /// blocks of nodes. Searchers that minimise other objectives return
+    /// `None`, and override [`try_move`](Self::try_move),
+    /// [`objective`](Self::objective) and [`get_cost`](Self::get_cost).
+    fn model(&self) -> Option<&dyn CostModel<N>>;
+
+    /// Whether the cache at `index` of the staircase is a vertical one.
+    fn is_vertical(&self, index: usize) -> bool;
+
+    /// Layout of the staircase of the searcher.
+    fn layout(&self) -> Layout;
+
+    /// Number of caches in the staircase.
+    fn ncaches(&self) -> usize {
+        self.filtered().len()
+    }
+
+    /// Whether `mv` keeps the cache at `index` inside the grid and between its
+    /// neighbours.
+    fn is_feasible(&self, index: usize, mv: Move<N>) -> bool {
+        let pos = self.filtered()[index];
+        let len = self.filtered().len();
+        let end = if self.is_vertical(index) {
+            self.height()
+        } else {
+            self.width()
+        };
+
+        match mv {
+            Move::Forward(step) => {
+                pos + step <= end && (index + 1 == len || pos + step <= self.filtered()[index + 1])
+            }
+            Move::Backward(step) => {
+                pos > step && (index == 0 || pos - step >= self.filtered()[index - 1])
+            }
+        }
+    }
 
-    fn try_advance_index(&mut self, index: usize) -> bool {
-        if self.filtered()[index] >= self.width()
-            || index + 1 < self.filtered().len()
-                && self.filtered()[index] >= self.filtered()[index + 1]
-        {
+    /// Whether the caches of every axis are strictly increasing and inside the
+    /// grid.
+    fn is_valid(&self) -> bool {
+        let valid = |mut caches: Box<dyn Iterator<Item = N> + '_>, end: N| {
+            caches
+                .try_fold(N::ZERO, |prev, pos| {
+                    (prev < pos && pos < end).then_some(pos)
+                })
+                .is_some()
+        };
+
+        valid(self.horizontal_caches(), self.width())
+            && valid(self.vertical_caches(), self.height())
+    }
+
+    /// Moves the cache at `index` of the staircase, as long as the move is
+    /// feasible.
+    fn move_cache(&mut self, index: usize, mv: Move<N>) -> Result<(), PlacementError> {
+        if index >= self.ncaches() {
+            return Err(PlacementError::NoSuchCache);
+        }
+        if !self.is_feasible(index, mv) {
+            return Err(PlacementError::Infeasible);
+        }
+
+        self.apply_move(index, mv);
+        Ok(())
+    }
+
+    /// Whether `mv` should be taken. Unit forward moves are also taken when
+    /// they keep the objective, so the caches can leave the packed initial
+    /// layout; the rest must strictly improve it.
+    fn try_move(&mut self, index: usize, mv: Move<N>) -> bool {
+        if !self.is_feasible(index, mv) {
             return false;
         }
 
+        let raw_index = self.filtered().raw_index(index);
+        let first = self.layout().first_axis();
+        let local = |searcher: &Self| {
+            searcher.model().expect(NO_MODEL).local(
+                searcher.filtered().get_raw_ref(),
+                first,
+                raw_index,
+            )
+        };
+
         let prev = self.filtered()[index];
-        let cost = cost_full(self.filtered().get_raw_ref());
-        self.filtered_mut()[index] += 1;
-        let new_cost = cost_full(self.filtered().get_raw_ref());
+        let cost = local(self);
+        self.apply_move(index, mv);
+        let new_cost = local(self);
         self.filtered_mut()[index] = prev;
 
-        new_cost <= cost
+        match mv {
+            Move::Forward(step) if step == N::ONE => new_cost <= cost,
+            _ => new_cost < cost,
+        }
     }
 
-    fn advance_index(&mut self, index: usize) {
-        self.filtered_mut()[index] += 1;
+    /// Value minimised by the search. It defaults to the cost of the served
+    /// nodes, without the contribution of the caches themselves.
+    fn objective(&self) -> N {
+        self.model()
+            .expect(NO_MODEL)
+            .full(self.filtered().get_raw_ref(), self.layout().first_axis())
     }
 
-    fn get_cost(&self) -> u64 {
-        let cache_costs: u64 = self.horizontal_caches().last().unwrap_or(0)
-            + self.vertical_caches().last().unwrap_or(0);
-
-        cache_costs + cost_full(self.filtered().get_raw_ref())
+    /// Cost of the current placement, including the distance from the
+    /// producer to the farthest cache of every axis.
+    fn get_cost(&self) -> N {
+        let cache_costs = self.horizontal_caches().last().unwrap_or(N::ZERO)
+            + self.vertical_caches().last().unwrap_or(N::ZERO);
+
+        cache_costs
+            + self
+                .model()
+                .expect(NO_MODEL)
+                .full(self.filtered().get_raw_ref(), self.layout().first_axis())
     }
 
-    fn vertical_caches(&self) -> Box<dyn Iterator<Item = u64> + '_>;
+    /// Current positions of the vertical caches.
+    fn vertical_caches(&self) -> Box<dyn Iterator<Item = N> + '_>;
 
-    fn horizontal_caches(&self) -> Box<dyn Iterator<Item = u64> + '_>;
+    /// Current positions of the horizontal caches.
+    fn horizontal_caches(&self) -> Box<dyn Iterator<Item = N> + '_>;
 
-    fn find_solution(&mut self) -> Solution<u64> {
-        assert!(self.filtered().len() != 0, "We need some caches");
-
-        let mut improves = false;
-        let mut pivot = self.filtered().len() - 1;
-        loop {
-            if self.try_advance_index(pivot) {
-                self.advance_index(pivot);
-                improves = true;
-            } else if pivot > 0 {
-                pivot -= 1;
+    /// Replaces the initial layout by `positions`, given in the order of the
+    /// staircase. The positions must be inside the grid and never decrease,
+    /// and there must be one for every cache.
+    fn warm_start(&mut self, positions: &[N]) -> Result<(), PlacementError> {
+        if positions.len() != self.ncaches() {
+            return Err(PlacementError::WrongCount);
+        }
+        let inside = positions.iter().enumerate().all(|(index, &pos)| {
+            let end = if self.is_vertical(index) {
+                self.height()
             } else {
-                if !improves {
-                    break;
-                }
-                pivot = self.filtered().len() - 1;
-                improves = false;
-            }
+                self.width()
+            };
+            N::ZERO < pos && pos <= end
+        });
+        if !inside {
+            return Err(PlacementError::OutOfGrid);
+        }
+        if positions.windows(2).any(|pair| pair[0] > pair[1]) {
+            return Err(PlacementError::Unordered);
         }
 
+        self.place(positions);
+        Ok(())
+    }
+
+    /// Iterator over the moves of the local search, which allows inspecting
+    /// the placement after every one of them.
+    fn steps(&mut self) -> Steps<'_, N, Self>
+    where
+        Self: Sized,
+    {
+        Steps::create(self, None)
+    }
+
+    /// Like [`steps`](Self::steps), but calling the hooks of `observer`.
+    fn steps_observed<'a>(&'a mut self, observer: &'a mut dyn Observer<N>) -> Steps<'a, N, Self>
+    where
+        Self: Sized,
+    {
+        Steps::create(self, Some(observer))
+    }
+
+    /// Current placement.
+    fn solution(&self) -> Solution<N> {
         Solution {
             cost: self.get_cost(),
             vertical_caches: self.vertical_caches().collect(),
             horizontal_caches: self.horizontal_caches().collect(),
         }
     }
+
+    /// Runs the search until it reaches a local optimum.
+    fn find_solution(&mut self) -> Solution<N> {
+        self.find_solution_observed(&mut ())
+    }
+
+    /// Like [`find_solution`](Self::find_solution), but calling the hooks of
+    /// `observer`.
+    fn find_solution_observed(&mut self, observer: &mut dyn Observer<N>) -> Solution<N> {
+        Steps::create(self, Some(observer)).for_each(drop);
+
+        self.solution()
+    }
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Solution<N> {
-    cost: u64,
+    cost: N,
     vertical_caches: Box<[N]>,
     horizontal_caches: Box<[N]>,
 }
 
 impl<N> Solution<N> {
+    pub(crate) fn create(cost: N, horizontal_caches: Box<[N]>, vertical_caches: Box<[N]>) -> Self {
+        Self {
+            cost,
+            vertical_caches,
+            horizontal_caches,
+        }
+    }
+
+    /// Placement without caches, where every node is served at `cost`.
+    pub fn without_caches(cost: N) -> Self {
+        Self::create(cost, Box::new([]), Box::new([]))
+    }
+
     /// Get the solution's cost.
-    pub fn cost(&self) -> u64 {
+    pub fn cost(&self) -> N
+    where
+        N: Copy,
+    {
         self.cost
     }
 
@@ -140,11 +357,40 @@ impl<N> Solution<N> {
     pub fn horizontal_caches(&self) -> &[N] {
         self.horizontal_caches.as_ref()
     }
+
+    /// Same placement in the transposed grid, where the horizontal caches
+    /// become the vertical ones and the other way round.
+    pub fn transposed(self) -> Self {
+        Self {
+            cost: self.cost,
+            vertical_caches: self.horizontal_caches,
+            horizontal_caches: self.vertical_caches,
+        }
+    }
+}
+
+impl Solution<u64> {
+    /// Relative gap between the cost of the solution and a lower bound of the
+    /// cost of any placement of as many caches in a `width`×`height` quadrant.
+    /// The optimal placement is at most this fraction cheaper.
+    ///
+    /// The bound is coarse, see [`get_lower_bound`](crate::cost::get_lower_bound),
+    /// so gaps of a third are common even for the best placements of a few
+    /// caches in both axes.
+    ///
+    /// For worst-case objectives the bound is that of the nominal cost, which
+    /// is never higher. Returns `None` for solutions without cost.
+    pub fn gap(&self, width: u64, height: u64) -> Option<f64> {
+        let ncaches = self.horizontal_caches.len() + self.vertical_caches.len();
+        let bound = get_budget_lower_bound(width, height, ncaches);
+
+        (self.cost > 0).then(|| self.cost.saturating_sub(bound) as f64 / self.cost as f64)
+    }
 }
 
 impl<N> PartialOrd for Solution<N>
 where
-    N: PartialEq,
+    N: PartialOrd,
 {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
         self.cost.partial_cmp(&other.cost)
@@ -153,17 +399,220 @@ where
 
 impl<N> Ord for Solution<N>
 where
-    N: Eq,
+    N: Ord,
 {
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
         self.cost.cmp(&other.cost)
     }
 }
 
-pub fn get_cache_locations(width: u64, height: u64, nhoriz: usize, nvert: usize) -> Solution<u64> {
+/// Initial layout of a searcher.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Layout {
+    HorizontalFirst,
+    VerticalFirst,
+}
+
+impl Layout {
+    /// Axis of the first block of the staircase.
+    fn first_axis(self) -> Axis {
+        match self {
+            Layout::HorizontalFirst => Axis::Vertical,
+            Layout::VerticalFirst => Axis::Horizontal,
+        }
+    }
+}
+
+impl Display for Layout {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Layout::HorizontalFirst => write!(f, "horizontal-first"),
+            Layout::VerticalFirst => write!(f, "vertical-first"),
+        }
+    }
+}
+
+/// Value to be minimised when looking for a placement.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Objective {
+    /// Cost when all the caches are working.
+    #[default]
+    Nominal,
+    /// Worst cost when up to `failures` caches go offline.
+    WorstCase { failures: usize },
+}
+
+impl Objective {
+    /// Nominal objective without failures, or the worst cost when up to
+    /// `failures` caches go offline.
+    pub fn from_failures(failures: usize) -> Self {
+        if failures > 0 {
+            Objective::WorstCase { failures }
+        } else {
+            Objective::Nominal
+        }
+    }
+
+    /// Checks that the objective does not allow more than [`MAX_FAILURES`]
+    /// failures.
+    pub fn validate(&self) -> Result<(), ProblemError> {
+        match *self {
+            Objective::WorstCase { failures } if failures > MAX_FAILURES => {
+                Err(ProblemError::TooManyFailures)
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+pub fn get_cache_locations<N: Integer>(
+    width: N,
+    height: N,
+    nhoriz: usize,
+    nvert: usize,
+) -> Solution<N> {
+    get_cache_locations_with(&HopCost, width, height, nhoriz, nvert)
+}
+
+/// Like [`get_cache_locations`], but minimising the cost given by `model`
+/// instead of the number of hops.
+///
+/// The cost of the solution is the cost of `model` plus the distance from the
+/// producer to the farthest cache of every axis. When there are more vertical
+/// caches than horizontal ones, the grid is searched transposed, with the
+/// model wrapped in [`Transposed`].
+pub fn get_cache_locations_with<N, M>(
+    model: &M,
+    width: N,
+    height: N,
+    nhoriz: usize,
+    nvert: usize,
+) -> Solution<N>
+where
+    N: Integer,
+    M: CostModel<N>,
+{
+    if nvert > nhoriz {
+        return search_layouts(&Transposed(model), height, width, nvert, nhoriz).transposed();
+    }
+
+    search_layouts(model, width, height, nhoriz, nvert)
+}
+
+/// Runs the searchers of both layouts with `model`, unless they are mirrored,
+/// and returns the best solution. There must not be more vertical caches than
+/// horizontal ones.
+fn search_layouts<N, M>(model: &M, width: N, height: N, nhoriz: usize, nvert: usize) -> Solution<N>
+where
+    N: Integer,
+    M: CostModel<N>,
+{
+    if model.is_symmetric() && is_mirrored(width, height, nhoriz, nvert) {
+        return SearcherFirstHorizontal::create_with(model, width, height, nhoriz, nvert)
+            .find_solution();
+    }
+
     let (sol_horiz, sol_vert) = rayon::join(
-        || SearcherFirstHorizontal::create(width, height, nhoriz, nvert).find_solution(),
-        || SearcherFirstVertical::create(width, height, nhoriz, nvert).find_solution(),
+        || {
+            SearcherFirstHorizontal::create_with(model, width, height, nhoriz, nvert)
+                .find_solution()
+        },
+        || SearcherFirstVertical::create_with(model, width, height, nhoriz, nvert).find_solution(),
+    );
+
+    min(sol_horiz, sol_vert)
+}
+
+/// Looks for the placement that minimises the worst cost when up to
+/// `failures` caches go offline. The cost of the returned solution is that
+/// worst-case cost.
+pub fn get_resilient_cache_locations(
+    width: u64,
+    height: u64,
+    nhoriz: usize,
+    nvert: usize,
+    failures: usize,
+) -> Solution<u64> {
+    get_objective_cache_locations(
+        width,
+        height,
+        nhoriz,
+        nvert,
+        Objective::WorstCase { failures },
+    )
+}
+
+/// Looks for the placement of `nhoriz` horizontal and `nvert` vertical caches
+/// that minimises `objective`.
+pub fn get_objective_cache_locations(
+    width: u64,
+    height: u64,
+    nhoriz: usize,
+    nvert: usize,
+    objective: Objective,
+) -> Solution<u64> {
+    search_both(width, height, nhoriz, nvert, objective, |searcher, _| {
+        searcher.find_solution()
+    })
+}
+
+/// Whether the vertical-first layout is the mirror image of the
+/// horizontal-first one. Then both searchers walk the same staircase, so just
+/// the horizontal-first one is run, and its solution is the canonical one.
+fn is_mirrored<N: Integer>(width: N, height: N, nhoriz: usize, nvert: usize) -> bool {
+    width == height && nhoriz == nvert
+}
+
+/// Runs `search` in parallel with the searchers of both layouts for
+/// `objective`, unless they are mirrored, and returns the best solution.
+pub(crate) fn search_both<F>(
+    width: u64,
+    height: u64,
+    nhoriz: usize,
+    nvert: usize,
+    objective: Objective,
+    search: F,
+) -> Solution<u64>
+where
+    F: Fn(&mut dyn Searcher<u64>, Layout) -> Solution<u64> + Sync,
+{
+    if nvert > nhoriz {
+        return search_both(height, width, nvert, nhoriz, objective, search).transposed();
+    }
+
+    let run = |layout| match (objective, layout) {
+        (Objective::Nominal, Layout::HorizontalFirst) => search(
+            &mut SearcherFirstHorizontal::create(width, height, nhoriz, nvert),
+            layout,
+        ),
+        (Objective::Nominal, Layout::VerticalFirst) => search(
+            &mut SearcherFirstVertical::create(width, height, nhoriz, nvert),
+            layout,
+        ),
+        (Objective::WorstCase { failures }, Layout::HorizontalFirst) => search(
+            &mut ResilientSearcher::create(
+                SearcherFirstHorizontal::create(width, height, nhoriz, nvert),
+                failures,
+            ),
+            layout,
+        ),
+        (Objective::WorstCase { failures }, Layout::VerticalFirst) => search(
+            &mut ResilientSearcher::create(
+                SearcherFirstVertical::create(width, height, nhoriz, nvert),
+                failures,
+            ),
+            layout,
+        ),
+    };
+
+    if is_mirrored(width, height, nhoriz, nvert) {
+        return run(Layout::HorizontalFirst);
+    }
+
+    let (sol_horiz, sol_vert) = rayon::join(
+        || run(Layout::HorizontalFirst),
+        || run(Layout::VerticalFirst),
     );
 
     min(sol_horiz, sol_vert)
@@ -171,7 +620,15 @@ pub fn get_cache_locations(width: u64, height: u64, nhoriz: usize, nvert: usize)
 
 #[cfg(test)]
 mod tests {
-    use crate::{get_cost, gridsearcher::get_cache_locations, Solution};
+    use crate::{
+        cost::{Axis, CostModel, HopCost},
+        get_cost,
+        gridsearcher::{
+            get_cache_locations, get_cache_locations_with, horizontal::SearcherFirstHorizontal,
+            sealed::Staircase, vertical::SearcherFirstVertical, Move, Searcher,
+        },
+        Solution,
+    };
 
     #[test]
     fn test_search_100_50_3_1() {
@@ -212,12 +669,188 @@ mod tests {
         let sol = get_cache_locations(100, 100, 1, 0);
 
         assert_eq!(sol.horizontal_caches(), [50]);
-        assert_eq!(sol.vertical_caches(), []);
+        assert_eq!(sol.vertical_caches(), [0u64; 0]);
         assert_eq!(sol.cost(), 740_050);
 
         check_solution(sol, 100, 100);
     }
 
+    #[test]
+    fn test_search_other_integers() {
+        let sol = get_cache_locations(100u64, 80, 2, 2);
+        let sol_u32 = get_cache_locations(100u32, 80, 2, 2);
+        let sol_u128 = get_cache_locations(100u128, 80, 2, 2);
+
+        assert_eq!(u64::from(sol_u32.cost()), sol.cost());
+        assert_eq!(sol_u128.cost(), u128::from(sol.cost()));
+        assert_eq!(sol_u32.horizontal_caches(), [44, 74]);
+        assert_eq!(sol_u128.vertical_caches(), [29, 58]);
+    }
+
+    #[test]
+    fn mirrored_layouts() {
+        let sol = get_cache_locations(80u64, 80, 2, 2);
+        let mirror = SearcherFirstVertical::<u64>::create(80, 80, 2, 2).find_solution();
+
+        assert_eq!(sol.cost(), mirror.cost());
+        assert_eq!(sol.horizontal_caches(), mirror.vertical_caches());
+        assert_eq!(sol.vertical_caches(), mirror.horizontal_caches());
+        assert!(sol.horizontal_caches()[0] < sol.vertical_caches()[0]);
+    }
+
+    #[test]
+    fn gap_of_solutions() {
+        assert!(get_cache_locations(100, 100, 1, 0).gap(100, 100).unwrap() < 1e-4);
+
+        let gap = get_cache_locations(100, 80, 2, 2).gap(100, 80).unwrap();
+        assert!(gap > 0.0 && gap < 0.5);
+
+        let free = Solution {
+            cost: 0,
+            vertical_caches: Box::new([]),
+            horizontal_caches: Box::new([1]),
+        };
+        assert_eq!(free.gap(1, 1), None);
+    }
+
+    /// Hops weighted by one plus the column of every node, so the cost
+    /// depends on the position and the axis of every block.
+    struct Eastward;
+
+    impl CostModel<u64> for Eastward {
+        fn block(&self, axis: Axis, start: u64, height: u64, end: u64) -> u64 {
+            (start..end)
+                .flat_map(|along| (0..height).map(move |across| (along, across)))
+                .map(|(along, across)| {
+                    let x = if axis == Axis::Horizontal {
+                        along
+                    } else {
+                        across
+                    };
+                    (1 + x) * (along - start + across)
+                })
+                .sum()
+        }
+    }
+
+    /// Cost of the placement under [`Eastward`], node by node.
+    fn eastward_cost(width: u64, height: u64, horiz: &[u64], vert: &[u64]) -> u64 {
+        let last_before = |caches: &[u64], pos| caches.iter().rev().find(|&&c| c <= pos).copied();
+        let nodes: u64 = (0..width)
+            .flat_map(|x| (0..height).map(move |y| (x, y)))
+            .map(|(x, y)| {
+                let served_by = last_before(horiz, x)
+                    .unwrap_or(0)
+                    .max(last_before(vert, y).unwrap_or(0));
+                (1 + x) * (x + y - served_by)
+            })
+            .sum();
+
+        nodes + horiz.last().unwrap_or(&0) + vert.last().unwrap_or(&0)
+    }
+
+    #[test]
+    fn position_dependent_model() {
+        let mut horiz = SearcherFirstHorizontal::create_with(Eastward, 30, 20, 2, 1);
+        let mut vert = SearcherFirstVertical::create_with(Eastward, 30, 20, 2, 1);
+        for sol in [horiz.find_solution(), vert.find_solution()] {
+            assert_eq!(
+                sol.cost(),
+                eastward_cost(30, 20, sol.horizontal_caches(), sol.vertical_caches())
+            );
+        }
+
+        for (width, height, nhoriz, nvert) in [(30, 20, 2, 1), (20, 30, 1, 2), (25, 25, 2, 2)] {
+            let sol = get_cache_locations_with(&Eastward, width, height, nhoriz, nvert);
+            assert_eq!(
+                sol.cost(),
+                eastward_cost(
+                    width,
+                    height,
+                    sol.horizontal_caches(),
+                    sol.vertical_caches()
+                )
+            );
+        }
+    }
+
+    #[test]
+    fn custom_move_loop() {
+        use crate::gridsearcher::{Move, PlacementError, Searcher, SearcherFirstHorizontal};
+
+        let mut searcher = SearcherFirstHorizontal::create(100u64, 50, 3, 1);
+        let initial = searcher.get_cost();
+        assert_eq!(
+            searcher.move_cache(0, Move::Backward(1)),
+            Err(PlacementError::Infeasible)
+        );
+        assert_eq!(
+            searcher.move_cache(searcher.ncaches(), Move::Forward(1)),
+            Err(PlacementError::NoSuchCache)
+        );
+
+        let mut improved = true;
+        while improved {
+            improved = false;
+            for index in 0..searcher.ncaches() {
+                for (mv, back) in [
+                    (Move::Forward(1), Move::Backward(1)),
+                    (Move::Backward(1), Move::Forward(1)),
+                ] {
+                    let cost = searcher.get_cost();
+                    if searcher.move_cache(index, mv).is_ok() {
+                        if searcher.get_cost() < cost {
+                            improved = true;
+                        } else {
+                            searcher.move_cache(index, back).unwrap();
+                        }
+                    }
+                }
+            }
+        }
+
+        assert!(searcher.get_cost() < initial);
+        assert!(searcher.is_valid());
+    }
+
+    #[test]
+    fn custom_cost_model() {
+        struct Scaled(u64);
+
+        impl CostModel<u64> for Scaled {
+            fn block(&self, axis: Axis, start: u64, height: u64, end: u64) -> u64 {
+                self.0 * HopCost.block(axis, start, height, end)
+            }
+        }
+
+        let sol = get_cache_locations_with(&Scaled(3), 100u64, 80, 2, 2);
+
+        assert_eq!(sol.horizontal_caches(), [44, 74]);
+        assert_eq!(sol.vertical_caches(), [29, 58]);
+        assert_eq!(sol.cost(), 3 * (350_224 - 74 - 58) + 74 + 58);
+    }
+
+    #[test]
+    fn recovers_from_overshooting() {
+        let mut searcher = SearcherFirstHorizontal::<u64>::create(100, 80, 2, 2);
+        searcher.warm_start(&[60, 70, 75, 78]).unwrap();
+        let start = searcher.get_cost();
+        let sol = searcher.find_solution();
+        assert!(sol.cost() < start);
+
+        let cost = searcher.objective();
+        for index in 0..searcher.filtered().len() {
+            for mv in [Move::Forward(1), Move::Backward(1)] {
+                if searcher.is_feasible(index, mv) {
+                    let prev = searcher.filtered()[index];
+                    searcher.apply_move(index, mv);
+                    assert!(searcher.objective() >= cost);
+                    searcher.filtered_mut()[index] = prev;
+                }
+            }
+        }
+    }
+
     fn check_solution(sol: Solution<u64>, width: u64, height: u64) {
         let full_v: Vec<_> = sol
             .vertical_caches()
@@ -0,0 +1,162 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+/*
+ *
+ * Copyright (c) 2022 Universidade de Vigo
+ *
+ * This program is free software; you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License version 2 as
+ * published by the Free Software Foundation;
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program; if not, write to the Free Software
+ * Foundation, Inc., 59 Temple Place, Suite 330, Boston, MA  02111-1307  USA
+ *
+ * Author: Miguel Rodríguez Pérez <miguel@det.uvigo.gal>
+ *
+ */
+
+use crate::{
+    cost::{get_worst_case_cost, CostModel},
+    filtered_slice::FilteredSlice,
+};
+
+use super::{sealed::Staircase, Layout, Move, Searcher};
+
+/// Wraps a searcher so that it minimises the worst cost when up to `failures`
+/// caches go offline, instead of the cost when all of them work.
+#[derive(Debug)]
+pub struct ResilientSearcher<S> {
+    searcher: S,
+    failures: usize,
+}
+
+impl<S> Staircase<u64> for ResilientSearcher<S>
+where
+    S: Searcher<u64>,
+{
+    fn filtered_mut(&mut self) -> &mut FilteredSlice<Vec<u64>, usize> {
+        self.searcher.filtered_mut()
+    }
+
+    fn filtered(&self) -> &FilteredSlice<Vec<u64>, usize> {
+        self.searcher.filtered()
+    }
+}
+
+impl<S> ResilientSearcher<S>
+where
+    S: Searcher<u64>,
+{
+    pub fn create(searcher: S, failures: usize) -> Self {
+        Self { searcher, failures }
+    }
+}
+
+impl<S> Searcher<u64> for ResilientSearcher<S>
+where
+    S: Searcher<u64>,
+{
+    /// The worst cost does not add up over blocks of nodes.
+    fn model(&self) -> Option<&dyn CostModel<u64>> {
+        None
+    }
+
+    fn is_vertical(&self, index: usize) -> bool {
+        self.searcher.is_vertical(index)
+    }
+
+    fn layout(&self) -> Layout {
+        self.searcher.layout()
+    }
+
+    fn width(&self) -> u64 {
+        self.searcher.width()
+    }
+
+    fn height(&self) -> u64 {
+        self.searcher.height()
+    }
+
+    /// Moves are only taken when they strictly reduce the worst cost, as many
+    /// placements share the same worst case and the caches would otherwise
+    /// drift without purpose.
+    fn try_move(&mut self, index: usize, mv: Move<u64>) -> bool {
+        if !self.is_feasible(index, mv) {
+            return false;
+        }
+
+        let prev = self.filtered()[index];
+        let cost = self.objective();
+        self.apply_move(index, mv);
+        let improves = self.is_valid() && self.objective() < cost;
+        self.filtered_mut()[index] = prev;
+
+        improves
+    }
+
+    fn objective(&self) -> u64 {
+        self.get_cost()
+    }
+
+    fn get_cost(&self) -> u64 {
+        let horiz: Vec<_> = self.horizontal_caches().collect();
+        let vert: Vec<_> = self.vertical_caches().collect();
+
+        get_worst_case_cost(self.width(), self.height(), &horiz, &vert, self.failures)
+    }
+
+    fn vertical_caches(&self) -> Box<dyn Iterator<Item = u64> + '_> {
+        self.searcher.vertical_caches()
+    }
+
+    fn horizontal_caches(&self) -> Box<dyn Iterator<Item = u64> + '_> {
+        self.searcher.horizontal_caches()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        cost::get_worst_case_cost,
+        gridsearcher::{horizontal::SearcherFirstHorizontal, Searcher},
+    };
+
+    use super::ResilientSearcher;
+
+    #[test]
+    fn no_failures_keeps_nominal_cost() {
+        let mut searcher =
+            ResilientSearcher::create(SearcherFirstHorizontal::<u64>::create(100, 100, 1, 0), 0);
+        let sol = searcher.find_solution();
+
+        assert_eq!(sol.horizontal_caches(), [50]);
+        assert_eq!(sol.cost(), 740_050);
+    }
+
+    #[test]
+    fn reports_worst_case_cost() {
+        let mut searcher =
+            ResilientSearcher::create(SearcherFirstHorizontal::<u64>::create(100, 60, 3, 1), 1);
+        let sol = searcher.find_solution();
+
+        assert_eq!(
+            sol.cost(),
+            get_worst_case_cost(100, 60, sol.horizontal_caches(), sol.vertical_caches(), 1)
+        );
+    }
+
+    #[test]
+    fn caches_do_not_overlap() {
+        let mut searcher =
+            ResilientSearcher::create(SearcherFirstHorizontal::<u64>::create(100, 40, 2, 0), 1);
+        let sol = searcher.find_solution();
+
+        assert!(sol.horizontal_caches()[0] < sol.horizontal_caches()[1]);
+        assert!(sol.horizontal_caches()[1] < 100);
+    }
+}
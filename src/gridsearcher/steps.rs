@@ -0,0 +1,286 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+/*
+ *
+ * Copyright (c) 2022 Universidade de Vigo
+ *
+ * This program is free software; you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License version 2 as
+ * published by the Free Software Foundation;
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program; if not, write to the Free Software
+ * Foundation, Inc., 59 Temple Place, Suite 330, Boston, MA  02111-1307  USA
+ *
+ * Author: Miguel Rodríguez Pérez <miguel@det.uvigo.gal>
+ *
+ */
+
+use crate::Integer;
+
+use super::{Move, Searcher};
+
+/// Number of moves tried between calls to [`Observer::should_stop`].
+const STOP_CHECK_PERIOD: usize = 64;
+
+/// Move taken by a search.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Step<N> {
+    /// Index of the moved cache in the filtered view of the searcher.
+    pub index: usize,
+    /// Displacement of the cache.
+    pub mv: Move<N>,
+    /// Sweep over all the caches in which the move was taken, starting at 0.
+    pub sweep: usize,
+}
+
+/// Hooks called while a search runs. Every hook does nothing by default.
+///
+/// The objective is passed as a function, so it is only computed for the
+/// observers that call it.
+pub trait Observer<N> {
+    /// Called before the search starts, with the objective of the initial
+    /// placement.
+    fn started(&mut self, _objective: &dyn Fn() -> N) {}
+
+    /// Called after trying to move the cache at `index`, whether the move was
+    /// taken or not.
+    fn tried(&mut self, _index: usize, _mv: Move<N>, _taken: bool) {}
+
+    /// Called after every move taken, with the objective of the new placement.
+    fn moved(&mut self, _step: &Step<N>, _objective: &dyn Fn() -> N) {}
+
+    /// Called at the end of every sweep over all the caches, with the size of
+    /// the moves tried and whether any cache moved.
+    fn swept(&mut self, _step_size: N, _improved: bool) {}
+
+    /// Called every few moves tried. The search stops, before reaching a local
+    /// optimum, as soon as it returns `true`.
+    fn should_stop(&mut self) -> bool {
+        false
+    }
+}
+
+impl<N> Observer<N> for () {}
+
+/// Iterator over the moves taken by the local search of a searcher, as
+/// returned by [`Searcher::steps`]. The search has finished, in a local
+/// optimum, when it returns `None`.
+pub struct Steps<'a, N, S: ?Sized> {
+    searcher: &'a mut S,
+    observer: Option<&'a mut dyn Observer<N>>,
+    step_size: N,
+    pivot: usize,
+    backward: bool,
+    improves: bool,
+    sweep: usize,
+    tries: usize,
+    done: bool,
+}
+
+impl<'a, N, S> Steps<'a, N, S>
+where
+    N: Integer,
+    S: Searcher<N> + ?Sized,
+{
+    pub(super) fn create(
+        searcher: &'a mut S,
+        mut observer: Option<&'a mut dyn Observer<N>>,
+    ) -> Self {
+        let len = searcher.filtered().len();
+        assert!(len != 0, "We need some caches");
+
+        if let Some(observer) = observer.as_mut() {
+            observer.started(&|| searcher.objective());
+        }
+
+        // Start with steps of about a quarter of the gap between caches and
+        // halve them once no cache can move, finishing with unit moves.
+        let step_size = (searcher.width() / N::from_usize(4 * (len + 1))).max(N::ONE);
+
+        Self {
+            searcher,
+            observer,
+            step_size,
+            pivot: len - 1,
+            backward: false,
+            improves: false,
+            sweep: 0,
+            tries: 0,
+            done: false,
+        }
+    }
+
+    /// Current state of the search.
+    pub fn searcher(&self) -> &S {
+        self.searcher
+    }
+}
+
+impl<N, S> Iterator for Steps<'_, N, S>
+where
+    N: Integer,
+    S: Searcher<N> + ?Sized,
+{
+    type Item = Step<N>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.done {
+            // Checking the deadline of a token in every try is noticeable
+            self.tries += 1;
+            if self.tries.is_multiple_of(STOP_CHECK_PERIOD)
+                && self
+                    .observer
+                    .as_mut()
+                    .is_some_and(|observer| observer.should_stop())
+            {
+                self.done = true;
+                break;
+            }
+
+            let mv = if self.backward {
+                Move::Backward(self.step_size)
+            } else {
+                Move::Forward(self.step_size)
+            };
+
+            // Keep moving the same cache in the same direction while it improves
+            let taken = self.searcher.try_move(self.pivot, mv);
+            if let Some(observer) = self.observer.as_mut() {
+                observer.tried(self.pivot, mv, taken);
+            }
+
+            if taken {
+                self.searcher.apply_move(self.pivot, mv);
+                self.improves = true;
+
+                let step = Step {
+                    index: self.pivot,
+                    mv,
+                    sweep: self.sweep,
+                };
+                if let Some(observer) = self.observer.as_mut() {
+                    let searcher = &*self.searcher;
+                    observer.moved(&step, &|| searcher.objective());
+                }
+                return Some(step);
+            }
+
+            if !self.backward {
+                self.backward = true;
+                continue;
+            }
+            self.backward = false;
+            if self.pivot > 0 {
+                self.pivot -= 1;
+                continue;
+            }
+
+            if let Some(observer) = self.observer.as_mut() {
+                observer.swept(self.step_size, self.improves);
+            }
+            if !self.improves {
+                if self.step_size == N::ONE {
+                    self.done = true;
+                    break;
+                }
+                self.step_size = self.step_size / N::TWO;
+            }
+            self.improves = false;
+            self.pivot = self.searcher.filtered().len() - 1;
+            self.sweep += 1;
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use crate::{
+        cost::{Axis, CostModel, HopCost},
+        gridsearcher::{horizontal::SearcherFirstHorizontal, Searcher},
+    };
+
+    use super::{Observer, Step};
+
+    #[derive(Default)]
+    struct Counter {
+        moves: usize,
+        sweeps: usize,
+    }
+
+    impl Observer<u64> for Counter {
+        fn moved(&mut self, _step: &Step<u64>, _objective: &dyn Fn() -> u64) {
+            self.moves += 1;
+        }
+
+        fn swept(&mut self, _step_size: u64, _improved: bool) {
+            self.sweeps += 1;
+        }
+    }
+
+    #[test]
+    fn every_step_improves() {
+        let mut searcher = SearcherFirstHorizontal::<u64>::create(100, 60, 3, 1);
+        let mut steps = searcher.steps();
+        let mut prev = steps.searcher().objective();
+
+        while let Some(step) = steps.next() {
+            let objective = steps.searcher().objective();
+            assert!(objective <= prev, "{step:?} does not improve");
+            prev = objective;
+        }
+
+        assert_eq!(searcher.solution().horizontal_caches(), [27, 54, 77]);
+    }
+
+    #[test]
+    fn observer_sees_every_move() {
+        let mut searcher = SearcherFirstHorizontal::<u64>::create(100, 60, 3, 1);
+        let moves = searcher.steps().count();
+
+        let mut counter = Counter::default();
+        let mut searcher = SearcherFirstHorizontal::<u64>::create(100, 60, 3, 1);
+        let sol = searcher.find_solution_observed(&mut counter);
+
+        assert_eq!(counter.moves, moves);
+        assert!(counter.sweeps > 0);
+        assert_eq!(sol.cost(), 220_407);
+    }
+
+    #[test]
+    fn objective_only_for_observers_using_it() {
+        #[derive(Default)]
+        struct Counted(AtomicUsize);
+
+        impl CostModel<u64> for Counted {
+            fn block(&self, axis: Axis, start: u64, height: u64, end: u64) -> u64 {
+                HopCost.block(axis, start, height, end)
+            }
+
+            fn full(&self, staircase: &[u64], first: Axis) -> u64 {
+                self.0.fetch_add(1, Ordering::Relaxed);
+                HopCost.full(staircase, first)
+            }
+
+            fn local(&self, staircase: &[u64], first: Axis, index: usize) -> u64 {
+                HopCost.local(staircase, first, index)
+            }
+        }
+
+        let model = Counted::default();
+        let mut searcher = SearcherFirstHorizontal::create_with(&model, 100u64, 60, 3, 1);
+        let mut counter = Counter::default();
+        let moves = searcher.steps_observed(&mut counter).count();
+
+        assert_eq!(counter.moves, moves);
+        assert_eq!(model.0.load(Ordering::Relaxed), 0);
+    }
+}
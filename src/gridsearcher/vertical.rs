@@ -20,19 +20,29 @@
  *
  */
 
-use crate::filtered_slice::FilteredSlice;
+use crate::{
+    cost::{CostModel, HopCost},
+    filtered_slice::FilteredSlice,
+    Integer,
+};
 
-use super::{Problem, Searcher};
+use super::{sealed::Staircase, Layout, Problem, Searcher};
 
 #[derive(Debug)]
-pub struct SearcherFirstVertical {
-    problem: Problem,
+pub struct SearcherFirstVertical<N, M = HopCost> {
+    problem: Problem<N, M>,
 }
-impl SearcherFirstVertical {
-    pub fn create(width: u64, height: u64, nhoriz: usize, nvert: usize) -> Self {
+impl<N: Integer> SearcherFirstVertical<N> {
+    pub fn create(width: N, height: N, nhoriz: usize, nvert: usize) -> Self {
+        Self::create_with(HopCost, width, height, nhoriz, nvert)
+    }
+}
+
+impl<N: Integer, M: CostModel<N>> SearcherFirstVertical<N, M> {
+    pub fn create_with(model: M, width: N, height: N, nhoriz: usize, nvert: usize) -> Self {
         assert!(nhoriz >= nvert);
-        assert!(width as usize > nhoriz);
-        assert!(height as usize > nvert);
+        assert!(width > N::from_usize(nhoriz));
+        assert!(height > N::from_usize(nvert));
 
         let caches: Vec<_> = [0]
             .into_iter() // Origin
@@ -40,10 +50,11 @@ impl SearcherFirstVertical {
                 // While there are vertical caches
                 (0..nvert).flat_map(|x| [2 * x + 1, 2 * (x + 1)].into_iter()),
             )
-            .map(|x| x as u64)
+            .map(N::from_usize)
             .chain(
                 // This is for horizontal ones
-                (0..nhoriz - nvert).flat_map(|x| [height, (2 * nvert + x + 1) as u64].into_iter()),
+                (0..nhoriz - nvert)
+                    .flat_map(|x| [height, N::from_usize(2 * nvert + x + 1)].into_iter()),
             )
             .chain([height, width])
             .collect();
@@ -59,14 +70,30 @@ impl SearcherFirstVertical {
                 nhoriz,
                 nvert,
                 width,
+                height,
                 filtered,
+                model,
             },
         }
     }
 }
 
-impl Searcher for SearcherFirstVertical {
-    fn vertical_caches(&self) -> Box<dyn Iterator<Item = u64> + '_> {
+impl<N: Integer, M: CostModel<N>> Staircase<N> for SearcherFirstVertical<N, M> {
+    fn filtered_mut(&mut self) -> &mut FilteredSlice<Vec<N>, usize> {
+        self.problem.filtered_mut()
+    }
+
+    fn filtered(&self) -> &FilteredSlice<Vec<N>, usize> {
+        self.problem.filtered()
+    }
+}
+
+impl<N: Integer, M: CostModel<N>> Searcher<N> for SearcherFirstVertical<N, M> {
+    fn model(&self) -> Option<&dyn CostModel<N>> {
+        Some(&self.problem.model)
+    }
+
+    fn vertical_caches(&self) -> Box<dyn Iterator<Item = N> + '_> {
         Box::new(
             (0..self.problem.nvert)
                 .map(|x| 2 * x + 1)
@@ -74,7 +101,7 @@ impl Searcher for SearcherFirstVertical {
         )
     }
 
-    fn horizontal_caches(&self) -> Box<dyn Iterator<Item = u64> + '_> {
+    fn horizontal_caches(&self) -> Box<dyn Iterator<Item = N> + '_> {
         Box::new(
             (0..self.problem.nhoriz)
                 .map(|x| 2 * (x + 1))
@@ -82,16 +109,20 @@ impl Searcher for SearcherFirstVertical {
         )
     }
 
-    fn width(&self) -> u64 {
-        self.problem.width
+    fn is_vertical(&self, index: usize) -> bool {
+        index < 2 * self.problem.nvert && index.is_multiple_of(2)
     }
 
-    fn filtered_mut(&mut self) -> &mut FilteredSlice<Vec<u64>, usize> {
-        self.problem.filtered_mut()
+    fn layout(&self) -> Layout {
+        Layout::VerticalFirst
     }
 
-    fn filtered(&self) -> &FilteredSlice<Vec<u64>, usize> {
-        self.problem.filtered()
+    fn width(&self) -> N {
+        self.problem.width
+    }
+
+    fn height(&self) -> N {
+        self.problem.height
     }
 }
 
@@ -101,7 +132,7 @@ mod tests {
 
     #[test]
     fn test_1_0() {
-        let searcher = SearcherFirstVertical::create(100, 50, 1, 0);
+        let searcher = SearcherFirstVertical::<u64>::create(100, 50, 1, 0);
         assert_eq!(
             searcher.problem.filtered.get_raw_ref(),
             &vec![0, 50, 1, 50, 100]
@@ -110,7 +141,7 @@ mod tests {
 
     #[test]
     fn test_5_0() {
-        let searcher = SearcherFirstVertical::create(100, 50, 5, 0);
+        let searcher = SearcherFirstVertical::<u64>::create(100, 50, 5, 0);
         assert_eq!(
             searcher.problem.filtered.get_raw_ref(),
             &vec![0, 50, 1, 50, 2, 50, 3, 50, 4, 50, 5, 50, 100]
@@ -122,7 +153,7 @@ mod tests {
 
     #[test]
     fn test_5_2() {
-        let searcher = SearcherFirstVertical::create(100, 50, 5, 2);
+        let searcher = SearcherFirstVertical::<u64>::create(100, 50, 5, 2);
         assert_eq!(
             searcher.problem.filtered.get_raw_ref(),
             &vec![0, 1, 2, 3, 4, 50, 5, 50, 6, 50, 7, 50, 100]
@@ -134,7 +165,7 @@ mod tests {
 
     #[test]
     fn test_search_100_50_3_1() {
-        let mut searcher = SearcherFirstVertical::create(100, 50, 3, 1);
+        let mut searcher = SearcherFirstVertical::<u64>::create(100, 50, 3, 1);
         let sol = searcher.find_solution();
 
         assert_eq!(sol.horizontal_caches(), [34, 56, 78]);
@@ -144,7 +175,7 @@ mod tests {
 
     #[test]
     fn test_search_100_80_2_2() {
-        let mut searcher = SearcherFirstVertical::create(100, 80, 2, 2);
+        let mut searcher = SearcherFirstVertical::<u64>::create(100, 80, 2, 2);
         let sol = searcher.find_solution();
 
         assert_eq!(sol.horizontal_caches(), [44, 74]);
@@ -155,11 +186,11 @@ mod tests {
 
     #[test]
     fn test_search_100_100_0_0() {
-        let mut searcher = SearcherFirstVertical::create(100, 100, 1, 0);
+        let mut searcher = SearcherFirstVertical::<u64>::create(100, 100, 1, 0);
         let sol = searcher.find_solution();
 
         assert_eq!(sol.horizontal_caches(), [50]);
-        assert_eq!(sol.vertical_caches(), []);
+        assert_eq!(sol.vertical_caches(), [0u64; 0]);
         assert_eq!(sol.cost(), 740_050);
     }
 }
@@ -0,0 +1,241 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+/*
+ *
+ * Copyright (c) 2022 Universidade de Vigo
+ *
+ * This program is free software; you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License version 2 as
+ * published by the Free Software Foundation;
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program; if not, write to the Free Software
+ * Foundation, Inc., 59 Temple Place, Suite 330, Boston, MA  02111-1307  USA
+ *
+ * Author: Miguel Rodríguez Pérez <miguel@det.uvigo.gal>
+ *
+ */
+
+use std::{cmp::min, error::Error, fmt::Display};
+
+use crate::Integer;
+
+use super::{
+    horizontal::SearcherFirstHorizontal, sealed::Staircase, vertical::SearcherFirstVertical,
+    Searcher, Solution,
+};
+
+/// Reasons why an initial placement cannot be used.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlacementError {
+    /// There are no caches at all.
+    NoCaches,
+    /// A cache is at the producer or outside the grid.
+    OutOfGrid,
+    /// The caches of an axis are not strictly increasing, or the caches of
+    /// both axes do not alternate.
+    Unordered,
+    /// The number of positions does not match the caches of the searcher.
+    WrongCount,
+    /// There is no cache at the given index of the staircase.
+    NoSuchCache,
+    /// The move takes a cache out of the grid or past one of its neighbours.
+    Infeasible,
+}
+
+impl Display for PlacementError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let msg = match self {
+            PlacementError::NoCaches => "There are no caches",
+            PlacementError::OutOfGrid => "A cache is at the producer or outside the grid",
+            PlacementError::Unordered => "Caches are not ordered or do not alternate",
+            PlacementError::WrongCount => "There is not a position for every cache",
+            PlacementError::NoSuchCache => "There is no cache at that index",
+            PlacementError::Infeasible => "The move leaves the grid or crosses another cache",
+        };
+
+        write!(f, "{msg}")
+    }
+}
+
+impl Error for PlacementError {}
+
+/// Merges both axes in the order followed by the searchers, starting by the
+/// first cache of `first`.
+fn interleave<N: Copy>(first: &[N], second: &[N]) -> Vec<N> {
+    let mut merged = Vec::with_capacity(first.len() + second.len());
+    for i in 0..first.len().max(second.len()) {
+        merged.extend(first.get(i));
+        merged.extend(second.get(i));
+    }
+
+    merged
+}
+
+fn is_ordered<N: Integer>(merged: &[N]) -> bool {
+    merged.windows(2).all(|pair| pair[0] <= pair[1])
+}
+
+fn is_strictly_increasing<N: Integer>(caches: &[N]) -> bool {
+    caches.windows(2).all(|pair| pair[0] < pair[1])
+}
+
+/// Looks for the placement of the caches starting from the given positions,
+/// instead of from the caches packed next to the producer.
+///
+/// The caches of each axis must be strictly increasing, and the caches of both
+/// axes must alternate, as in the solutions returned by
+/// [`get_cache_locations`](super::get_cache_locations), while there are
+/// vertical ones.
+pub fn get_cache_locations_from<N: Integer>(
+    width: N,
+    height: N,
+    horizontal: &[N],
+    vertical: &[N],
+) -> Result<Solution<N>, PlacementError> {
+    let (nhoriz, nvert) = (horizontal.len(), vertical.len());
+    if nhoriz < nvert {
+        return get_cache_locations_from(height, width, vertical, horizontal)
+            .map(Solution::transposed);
+    }
+    if nhoriz == 0 {
+        return Err(PlacementError::NoCaches);
+    }
+    if horizontal[0] == N::ZERO
+        || vertical.first() == Some(&N::ZERO)
+        || horizontal[nhoriz - 1] > width
+        || vertical.last().is_some_and(|&last| last > height)
+        || width <= N::from_usize(nhoriz)
+        || height <= N::from_usize(nvert)
+    {
+        return Err(PlacementError::OutOfGrid);
+    }
+    if !is_strictly_increasing(horizontal) || !is_strictly_increasing(vertical) {
+        return Err(PlacementError::Unordered);
+    }
+
+    let horizontal_first = interleave(horizontal, vertical);
+    let vertical_first = interleave(vertical, &horizontal[..nvert])
+        .into_iter()
+        .chain(horizontal[nvert..].iter().copied())
+        .collect::<Vec<_>>();
+
+    let solve_horizontal = || {
+        let mut searcher = SearcherFirstHorizontal::create(width, height, nhoriz, nvert);
+        searcher.place(&horizontal_first);
+        searcher.find_solution()
+    };
+    let solve_vertical = || {
+        let mut searcher = SearcherFirstVertical::create(width, height, nhoriz, nvert);
+        searcher.place(&vertical_first);
+        searcher.find_solution()
+    };
+
+    match (is_ordered(&horizontal_first), is_ordered(&vertical_first)) {
+        (true, true) => {
+            let (sol_horiz, sol_vert) = rayon::join(solve_horizontal, solve_vertical);
+            Ok(min(sol_horiz, sol_vert))
+        }
+        (true, false) => Ok(solve_horizontal()),
+        (false, true) => Ok(solve_vertical()),
+        (false, false) => Err(PlacementError::Unordered),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{get_cache_locations, get_cost};
+
+    use super::{get_cache_locations_from, PlacementError, Searcher, SearcherFirstHorizontal};
+
+    #[test]
+    fn start_at_solution() {
+        let sol = get_cache_locations(100u64, 80, 2, 2);
+        let warm =
+            get_cache_locations_from(100, 80, sol.horizontal_caches(), sol.vertical_caches())
+                .unwrap();
+
+        assert_eq!(warm, sol);
+    }
+
+    #[test]
+    fn start_at_smaller_grid() {
+        let small = get_cache_locations(90u64, 50, 3, 1);
+        let warm =
+            get_cache_locations_from(100, 50, small.horizontal_caches(), small.vertical_caches())
+                .unwrap();
+
+        assert!(
+            warm.cost()
+                <= get_cost(
+                    &[small.horizontal_caches(), &[100]].concat(),
+                    &[small.vertical_caches(), &[50]].concat()
+                )
+        );
+    }
+
+    #[test]
+    fn start_beyond_solution() {
+        let warm = get_cache_locations_from(100u64, 50, &[45, 70, 90], &[40]).unwrap();
+
+        assert!(warm.cost() < get_cost(&[45, 70, 90, 100], &[40, 50]));
+        assert_eq!(warm.vertical_caches(), [25]);
+    }
+
+    #[test]
+    fn more_vertical_caches() {
+        let sol = get_cache_locations_from(50u64, 100, &[40], &[45, 70, 90]).unwrap();
+
+        assert_eq!(
+            sol,
+            get_cache_locations_from(100u64, 50, &[45, 70, 90], &[40])
+                .unwrap()
+                .transposed()
+        );
+    }
+
+    #[test]
+    fn invalid_placements() {
+        assert_eq!(
+            get_cache_locations_from(100u64, 80, &[], &[]),
+            Err(PlacementError::NoCaches)
+        );
+        assert_eq!(
+            get_cache_locations_from(100u64, 80, &[10, 120], &[5]),
+            Err(PlacementError::OutOfGrid)
+        );
+        assert_eq!(
+            get_cache_locations_from(100u64, 80, &[30, 20], &[]),
+            Err(PlacementError::Unordered)
+        );
+        assert_eq!(
+            get_cache_locations_from(100u64, 80, &[10, 20], &[30, 40]),
+            Err(PlacementError::Unordered)
+        );
+    }
+
+    #[test]
+    fn warm_start() {
+        let mut searcher = SearcherFirstHorizontal::create(100u64, 80, 2, 1);
+
+        assert_eq!(
+            searcher.warm_start(&[10, 20]),
+            Err(PlacementError::WrongCount)
+        );
+        assert_eq!(
+            searcher.warm_start(&[10, 90, 95]),
+            Err(PlacementError::OutOfGrid)
+        );
+        assert_eq!(
+            searcher.warm_start(&[30, 20, 40]),
+            Err(PlacementError::Unordered)
+        );
+        assert_eq!(searcher.warm_start(&[10, 20, 30]), Ok(()));
+        assert_eq!(searcher.horizontal_caches().collect::<Vec<_>>(), [10, 30]);
+        assert_eq!(searcher.vertical_caches().collect::<Vec<_>>(), [20]);
+    }
+}
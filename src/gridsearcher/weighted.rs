@@ -0,0 +1,87 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+/*
+ *
+ * Copyright (c) 2022 Universidade de Vigo
+ *
+ * This program is free software; you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License version 2 as
+ * published by the Free Software Foundation;
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program; if not, write to the Free Software
+ * Foundation, Inc., 59 Temple Place, Suite 330, Boston, MA  02111-1307  USA
+ *
+ * Author: Miguel Rodríguez Pérez <miguel@det.uvigo.gal>
+ *
+ */
+
+use crate::demand::Demand;
+
+use super::{get_cache_locations_with, Solution};
+
+/// Looks for the placement of `nhoriz` horizontal and `nvert` vertical caches
+/// that minimises the hops weighted by `demand`. The cost of the returned
+/// solution is that weighted cost.
+pub fn get_weighted_cache_locations(demand: &Demand, nhoriz: usize, nvert: usize) -> Solution<u64> {
+    get_cache_locations_with(demand, demand.width(), demand.height(), nhoriz, nvert)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        demand::{Demand, DemandModel},
+        get_cache_locations,
+    };
+
+    use super::get_weighted_cache_locations;
+
+    #[test]
+    fn uniform_demand_matches_hops() {
+        let sol = get_weighted_cache_locations(&Demand::uniform(100, 60), 3, 1);
+
+        assert_eq!(sol.cost(), get_cache_locations(100u64, 60, 3, 1).cost());
+    }
+
+    #[test]
+    fn caches_follow_the_hotspot() {
+        let near = DemandModel::Hotspot {
+            x: 20.0,
+            y: 5.0,
+            sigma: 8.0,
+        }
+        .generate(100, 60);
+        let far = DemandModel::Hotspot {
+            x: 80.0,
+            y: 5.0,
+            sigma: 8.0,
+        }
+        .generate(100, 60);
+
+        let sol_near = get_weighted_cache_locations(&near, 2, 1);
+        let sol_far = get_weighted_cache_locations(&far, 2, 1);
+
+        assert!(sol_near.horizontal_caches()[1] < sol_far.horizontal_caches()[1]);
+        assert_eq!(
+            sol_far.cost(),
+            far.cost(sol_far.horizontal_caches(), sol_far.vertical_caches())
+        );
+    }
+
+    #[test]
+    fn more_vertical_caches() {
+        let demand = DemandModel::Bands { bands: 2 }.generate(40, 90);
+        let sol = get_weighted_cache_locations(&demand, 1, 3);
+
+        assert_eq!(sol.horizontal_caches().len(), 1);
+        assert_eq!(sol.vertical_caches().len(), 3);
+        assert_eq!(
+            sol.cost(),
+            demand.cost(sol.horizontal_caches(), sol.vertical_caches())
+        );
+    }
+}
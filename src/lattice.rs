@@ -0,0 +1,273 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+/*
+ *
+ * Copyright (c) 2022 Universidade de Vigo
+ *
+ * This program is free software; you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License version 2 as
+ * published by the Free Software Foundation;
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program; if not, write to the Free Software
+ * Foundation, Inc., 59 Temple Place, Suite 330, Boston, MA  02111-1307  USA
+ *
+ * Author: Miguel Rodríguez Pérez <miguel@det.uvigo.gal>
+ *
+ */
+
+//! Cache placement in three dimensional lattices, as those formed by
+//! constellations with several orbital shells linked between them.
+
+use itertools::Itertools;
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+
+use crate::cost::get_cuboid_cost;
+
+/// Placement of caches in the x, y and z axes of a lattice.
+#[derive(Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LatticeSolution<N> {
+    cost: u64,
+    x_caches: Box<[N]>,
+    y_caches: Box<[N]>,
+    z_caches: Box<[N]>,
+}
+
+impl<N> LatticeSolution<N> {
+    /// Get the solution's cost.
+    pub fn cost(&self) -> u64 {
+        self.cost
+    }
+
+    /// Get a reference to the solution's caches in the x axis.
+    pub fn x_caches(&self) -> &[N] {
+        self.x_caches.as_ref()
+    }
+
+    /// Get a reference to the solution's caches in the y axis.
+    pub fn y_caches(&self) -> &[N] {
+        self.y_caches.as_ref()
+    }
+
+    /// Get a reference to the solution's caches in the z axis.
+    pub fn z_caches(&self) -> &[N] {
+        self.z_caches.as_ref()
+    }
+}
+
+impl<N> PartialOrd for LatticeSolution<N>
+where
+    N: Eq,
+{
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<N> Ord for LatticeSolution<N>
+where
+    N: Eq,
+{
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.cost.cmp(&other.cost)
+    }
+}
+
+/// Local searcher for the three interleaved cache lists of a lattice.
+///
+/// The caches start packed next to the producer and are moved away from it one
+/// step at a time, visiting them in the order given when created.
+#[derive(Debug)]
+struct LatticeSearcher {
+    extents: [u64; 3],
+    caches: [Vec<u64>; 3],
+    /// Pairs of (axis, position in the axis) in visiting order.
+    order: Vec<(usize, usize)>,
+}
+
+impl LatticeSearcher {
+    fn create(extents: [u64; 3], ncaches: [usize; 3], axes: [usize; 3]) -> Self {
+        for (&extent, &n) in extents.iter().zip(&ncaches) {
+            assert!(extent as usize > n);
+        }
+
+        let caches = ncaches.map(|n| (1..=n as u64).collect());
+        let order = (0..ncaches.into_iter().max().unwrap_or(0))
+            .flat_map(|i| axes.into_iter().map(move |axis| (axis, i)))
+            .filter(|&(axis, i)| i < ncaches[axis])
+            .collect();
+
+        Self {
+            extents,
+            caches,
+            order,
+        }
+    }
+
+    fn get_cost(&self) -> u64 {
+        let [width, height, depth] = self.extents;
+        let [x, y, z] = &self.caches;
+
+        get_cuboid_cost(width, height, depth, x, y, z)
+    }
+
+    /// Value minimised by the search. As in the grid searchers, this is the
+    /// cost of the served nodes, without the contribution of the caches.
+    fn objective(&self) -> u64 {
+        self.get_cost() - self.caches.iter().filter_map(|c| c.last()).sum::<u64>()
+    }
+
+    fn try_advance_index(&mut self, index: usize) -> bool {
+        let (axis, i) = self.order[index];
+        let caches = &self.caches[axis];
+        let next = caches.get(i + 1).copied().unwrap_or(self.extents[axis]);
+
+        if caches[i] + 1 >= next {
+            return false;
+        }
+
+        let cost = self.objective();
+        self.caches[axis][i] += 1;
+        let new_cost = self.objective();
+        self.caches[axis][i] -= 1;
+
+        new_cost <= cost
+    }
+
+    fn advance_index(&mut self, index: usize) {
+        let (axis, i) = self.order[index];
+        self.caches[axis][i] += 1;
+    }
+
+    fn find_solution(mut self) -> LatticeSolution<u64> {
+        if !self.order.is_empty() {
+            let mut improves = false;
+            let mut pivot = self.order.len() - 1;
+            loop {
+                if self.try_advance_index(pivot) {
+                    self.advance_index(pivot);
+                    improves = true;
+                } else if pivot > 0 {
+                    pivot -= 1;
+                } else {
+                    if !improves {
+                        break;
+                    }
+                    pivot = self.order.len() - 1;
+                    improves = false;
+                }
+            }
+        }
+
+        let cost = self.get_cost();
+        let [x_caches, y_caches, z_caches] = self.caches.map(Vec::into_boxed_slice);
+        LatticeSolution {
+            cost,
+            x_caches,
+            y_caches,
+            z_caches,
+        }
+    }
+}
+
+/// Looks for the placement of `nx`, `ny` and `nz` caches in the axes of a
+/// `width`×`height`×`depth` lattice with the producer in its center.
+///
+/// Every order of the axes is tried as the starting layout and the best result
+/// is kept, as [`get_cache_locations`](crate::get_cache_locations) does with
+/// the horizontal and vertical layouts.
+pub fn get_lattice_cache_locations(
+    width: u64,
+    height: u64,
+    depth: u64,
+    nx: usize,
+    ny: usize,
+    nz: usize,
+) -> LatticeSolution<u64> {
+    (0..3)
+        .permutations(3)
+        .collect_vec()
+        .into_par_iter()
+        .map(|axes| {
+            LatticeSearcher::create(
+                [width, height, depth],
+                [nx, ny, nz],
+                [axes[0], axes[1], axes[2]],
+            )
+            .find_solution()
+        })
+        .min()
+        .expect("there is always an order of the axes")
+}
+
+/// Looks for the best way of splitting `ncaches` between the three axes of a
+/// lattice, and for the placement of the caches in that split.
+///
+/// Returns `None` if there is no valid split for the lattice.
+pub fn find_best_lattice_split(
+    width: u64,
+    height: u64,
+    depth: u64,
+    ncaches: usize,
+) -> Option<LatticeSolution<u64>> {
+    (0..=ncaches)
+        .flat_map(|nx| (0..=ncaches - nx).map(move |ny| (nx, ny, ncaches - nx - ny)))
+        .filter(|&(nx, ny, nz)| width > nx as u64 && height > ny as u64 && depth > nz as u64)
+        .collect_vec()
+        .into_par_iter()
+        .map(|(nx, ny, nz)| get_lattice_cache_locations(width, height, depth, nx, ny, nz))
+        .min()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cost::get_cuboid_cost;
+
+    use super::{find_best_lattice_split, get_lattice_cache_locations, LatticeSolution};
+
+    #[test]
+    fn search_40_30_20_2_1_1() {
+        let sol = get_lattice_cache_locations(40, 30, 20, 2, 1, 1);
+
+        assert_eq!(sol.x_caches().len(), 2);
+        assert_eq!(sol.y_caches().len(), 1);
+        assert_eq!(sol.z_caches().len(), 1);
+
+        check_solution(&sol, 40, 30, 20);
+    }
+
+    #[test]
+    fn cube_split_is_balanced() {
+        let sol = find_best_lattice_split(30, 30, 30, 3).unwrap();
+
+        assert_eq!(sol.x_caches().len(), 1);
+        assert_eq!(sol.y_caches().len(), 1);
+        assert_eq!(sol.z_caches().len(), 1);
+
+        check_solution(&sol, 30, 30, 30);
+    }
+
+    #[test]
+    fn no_valid_split() {
+        assert_eq!(find_best_lattice_split(2, 2, 2, 4), None);
+    }
+
+    fn check_solution(sol: &LatticeSolution<u64>, width: u64, height: u64, depth: u64) {
+        assert_eq!(
+            sol.cost(),
+            get_cuboid_cost(
+                width,
+                height,
+                depth,
+                sol.x_caches(),
+                sol.y_caches(),
+                sol.z_caches()
+            )
+        );
+    }
+}
@@ -20,10 +20,43 @@
  *
  */
 
+pub mod cancel;
+#[cfg(feature = "capi")]
+pub mod capi;
 pub mod cost;
+pub mod demand;
+pub mod export;
 mod filtered_slice;
-mod gridsearcher;
+pub mod gridsearcher;
+pub mod lattice;
+mod num;
+pub mod offaxis;
+pub mod parallelism;
+mod problem;
+pub mod progress;
+#[cfg(feature = "python")]
+mod python;
+pub mod quadrants;
+mod rng;
+#[cfg(feature = "server")]
+pub mod server;
+mod split;
+pub mod store;
+pub mod topology;
+pub mod trace;
 
 pub use cost::get_cost;
+pub use cost::{Axis, CostModel, HopCost};
+pub use gridsearcher::get_annealed_cache_locations;
 pub use gridsearcher::get_cache_locations;
+pub use gridsearcher::get_cache_locations_from;
+pub use gridsearcher::get_cache_locations_with;
+pub use gridsearcher::get_resilient_cache_locations;
+pub use gridsearcher::get_weighted_cache_locations;
+pub use gridsearcher::AnnealParams;
+pub use gridsearcher::Objective;
+pub use gridsearcher::PlacementError;
 pub use gridsearcher::Solution;
+pub use num::Integer;
+pub use problem::{ProblemDescription, ProblemError, MAX_FAILURES};
+pub use split::{find_best_split, find_best_weighted_split, SplitOptions, SplitSolver};
@@ -20,23 +20,51 @@
  *
  */
 
-use std::process::exit;
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+    process::exit,
+    sync::Mutex,
+    time::Duration,
+};
 
-use clap::Parser;
-use fastgridcache::get_cache_locations;
-
-use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use clap::{Parser, Subcommand, ValueEnum};
+use fastgridcache::{
+    cancel::{CancelToken, Outcome},
+    cost::get_grid_cost,
+    demand::DemandModel,
+    export::{nfd_fragments, write_ndnsim_topology, ExportParams},
+    find_best_split, find_best_weighted_split,
+    lattice::find_best_lattice_split,
+    offaxis::{compare_with_in_axis, is_exact_feasible, Method, MAX_EXACT_PLACEMENTS},
+    progress::Progress,
+    store::SolutionStore,
+    topology::{get_tree_cache_locations, Topology},
+    trace::TraceEvent,
+    AnnealParams, Objective, Solution, SplitOptions, SplitSolver,
+};
 
 #[derive(Parser, Debug)]
-#[clap(author, version, about, long_about = None)]
+#[clap(
+    author,
+    version,
+    about,
+    long_about = None,
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true
+)]
 struct Args {
+    #[clap(subcommand)]
+    command: Option<Command>,
+
     /// Width of the network grid
-    #[clap(short, long)]
-    width: u64,
+    #[clap(short, long, required_unless_present = "topology")]
+    width: Option<u64>,
 
     /// Height of the network grid
-    #[clap(short = 'e', long)]
-    height: u64,
+    #[clap(short = 'e', long, required_unless_present = "topology")]
+    height: Option<u64>,
 
     /// Number of caches
     #[clap(short, long, default_value_t = 0)]
@@ -47,44 +75,502 @@ struct Args {
 
     #[clap(short, long)]
     show_caches: bool,
+
+    /// Show the gap to a coarse lower bound of the cost of any placement
+    #[clap(
+        short,
+        long,
+        conflicts_with_all = ["depth", "off_axis", "topology", "demand_model"]
+    )]
+    gap: bool,
+
+    /// Minimise the worst cost when up to this number of caches fail, at most 4
+    #[clap(short = 'k', long, default_value_t = 0)]
+    failures: usize,
+
+    /// Depth of the network lattice, for constellations with several shells
+    #[clap(short, long, conflicts_with = "failures")]
+    depth: Option<u64>,
+
+    /// Allow caches anywhere in the grid and compare with the in-axis placement
+    #[clap(short, long, conflicts_with_all = ["failures", "depth"])]
+    off_axis: bool,
+
+    /// Solve the off-axis placement exactly. Only feasible for tiny grids
+    #[clap(long, requires = "off_axis")]
+    exact: bool,
+
+    /// Edge list of an arbitrary network topology, instead of a grid
+    #[clap(
+        short,
+        long,
+        requires = "producer",
+        conflicts_with_all = ["width", "height", "failures", "depth", "off_axis"]
+    )]
+    topology: Option<PathBuf>,
+
+    /// ID of the producer node in the topology
+    #[clap(short, long, requires = "topology")]
+    producer: Option<String>,
+
+    /// Algorithm used to place the caches in the grid
+    #[clap(
+        long,
+        value_enum,
+        default_value_t = Solver::Local,
+        conflicts_with_all = ["depth", "off_axis", "topology", "cache"]
+    )]
+    solver: Solver,
+
+    /// Seed of the annealing solver [default: 0]
+    #[clap(long)]
+    seed: Option<u64>,
+
+    /// Write every step of the local search of every split to this file, as
+    /// JSON Lines
+    #[clap(long, conflicts_with_all = ["depth", "off_axis", "topology"])]
+    trace: Option<PathBuf>,
+
+    /// Stop the search after this number of seconds and show the best
+    /// placement found by then
+    #[clap(
+        long,
+        value_name = "SECONDS",
+        conflicts_with_all = ["depth", "off_axis", "topology"]
+    )]
+    time_limit: Option<f64>,
+
+    /// Report on stderr every split searched, with the best cost so far and
+    /// the estimated time left
+    #[clap(long, conflicts_with_all = ["depth", "off_axis", "topology"])]
+    progress: bool,
+
+    /// Weight the hops of every node with a synthetic demand: uniform,
+    /// hotspot:x,y,sigma, bands:count or random:seed
+    #[clap(
+        long,
+        value_name = "MODEL",
+        conflicts_with_all = [
+            "failures", "depth", "off_axis", "topology", "cache", "solver", "trace", "time_limit",
+            "progress"
+        ]
+    )]
+    demand_model: Option<DemandModel>,
+
+    /// Number of threads of the search. Use 1 for a sequential search
+    /// [default: one per CPU]
+    #[clap(long)]
+    threads: Option<usize>,
+
+    /// Reuse the solutions stored in the cache directory, and store new ones
+    #[clap(long, conflicts_with_all = ["depth", "off_axis", "topology"])]
+    cache: bool,
+
+    /// Cache directory [default: $XDG_CACHE_HOME/fastgridcache]
+    #[clap(long, requires = "cache", conflicts_with_all = ["depth", "off_axis", "topology"])]
+    cache_dir: Option<PathBuf>,
+
+    /// Remove every stored solution before solving
+    #[clap(long, requires = "cache", conflicts_with_all = ["depth", "off_axis", "topology"])]
+    clear_cache: bool,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum Solver {
+    /// Local search from packed caches
+    Local,
+    /// Simulated annealing with parallel restarts
+    Anneal,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Answer placement requests over HTTP with JSON bodies
+    Serve {
+        /// Address to listen on
+        #[clap(short, long, default_value = "127.0.0.1:8080")]
+        listen: String,
+
+        /// Number of threads that solve the requests [default: one per CPU]
+        #[clap(long)]
+        threads: Option<usize>,
+    },
+    /// Compute the placement with the lowest nominal hop cost and export it to
+    /// the configuration of an NDN emulation
+    Export {
+        /// Width of the network grid
+        #[clap(short, long)]
+        width: u64,
+
+        /// Height of the network grid
+        #[clap(short = 'e', long)]
+        height: u64,
+
+        /// Number of caches
+        #[clap(short, long)]
+        ncaches: u16,
+
+        /// Configuration to write
+        #[clap(short, long, value_enum, default_value_t = ExportFormat::Ndnsim)]
+        format: ExportFormat,
+
+        /// Content Store size of the caches, in packets
+        #[clap(long, default_value_t = 1000)]
+        cs_size: u64,
+
+        /// Output file for ndnsim [default: stdout], or output directory for
+        /// nfd, with a fragment per node
+        #[clap(short, long, required_if_eq("format", "nfd"))]
+        output: Option<PathBuf>,
+    },
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum ExportFormat {
+    /// ndnSIM annotated topology
+    Ndnsim,
+    /// NFD configuration fragments
+    Nfd,
 }
+
+impl Args {
+    fn width(&self) -> u64 {
+        self.width.expect("Clap requires the width for grids")
+    }
+
+    fn height(&self) -> u64 {
+        self.height.expect("Clap requires the height for grids")
+    }
+}
+
 fn main() {
     let args = Args::parse();
 
-    if args.width < args.height {
-        eprintln!("Grid cannot be taller than wider.");
-        exit(exitcode::DATAERR);
-    }
-
-    let (best_cost, best_w, best_h) = match (0..=args.ncaches / 2)
-        .into_par_iter()
-        .filter(|&vcaches_n| args.height > vcaches_n.into())
-        .map(|vcaches_n| {
-            let sol = get_cache_locations(
-                args.width,
-                args.height,
-                (args.ncaches - vcaches_n).into(),
-                vcaches_n.into(),
-            );
-
-            (
-                sol.cost(),
-                sol.horizontal_caches().to_owned(),
-                sol.vertical_caches().to_owned(),
+    match &args.command {
+        Some(Command::Serve { listen, threads }) => serve(listen, *threads),
+        Some(Command::Export {
+            width,
+            height,
+            ncaches,
+            format,
+            cs_size,
+            output,
+        }) => {
+            let params = ExportParams {
+                cs_size: *cs_size,
+                ..Default::default()
+            };
+            export(
+                *width,
+                *height,
+                *ncaches,
+                *format,
+                &params,
+                output.as_deref(),
             )
-        })
-        .min_by(|(cost1, _w1, _h1), (cost2, _w2, _h2)| cost1.cmp(cost2))
+        }
+        None => (),
+    }
+
+    if args.seed.is_some() && args.solver != Solver::Anneal {
+        eprintln!("The seed is only used by the annealing solver, --solver anneal.");
+        exit(exitcode::USAGE);
+    }
+
+    if let Some(threads) = args.threads {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build_global();
+        if let Err(err) = pool {
+            eprintln!("Cannot start the threads: {err}");
+            exit(exitcode::OSERR);
+        }
+    }
+
+    if let Some(path) = &args.topology {
+        solve_topology(&args, path);
+    }
+
+    if let Some(depth) = args.depth {
+        solve_lattice(&args, depth);
+    }
+    if args.off_axis {
+        solve_off_axis(&args);
+    }
+
+    let objective = Objective::from_failures(args.failures);
+    if let Err(err) = objective.validate() {
+        eprintln!("{err}");
+        exit(exitcode::USAGE);
+    }
+
+    let demand = args
+        .demand_model
+        .map(|model| model.generate(args.width(), args.height()));
+    let best = match &demand {
+        Some(demand) if args.ncaches == 0 => Some(Solution::without_caches(demand.cost(&[], &[]))),
+        None if args.ncaches == 0 => Some(Solution::without_caches(get_grid_cost(
+            args.width(),
+            args.height(),
+            &[],
+            &[],
+        ))),
+        Some(demand) => find_best_weighted_split(demand, args.ncaches.into()),
+        None => solve_splits(&args, objective),
+    };
+    let Some(sol) = best else { no_placement() };
+
+    if !args.hide_cost {
+        println!("{}", sol.cost());
+    }
+    if args.show_caches {
+        println!("{:?}×{:?}", sol.horizontal_caches(), sol.vertical_caches());
+    }
+    if args.gap {
+        if let Some(gap) = sol.gap(args.width(), args.height()) {
+            println!("{gap:.6}");
+        }
+    }
+
+    exit(exitcode::OK);
+}
+
+/// Exits when the caches do not fit in the grid, the same way in every mode.
+fn no_placement() -> ! {
+    eprintln!("The caches do not fit in the grid.");
+    exit(exitcode::DATAERR);
+}
+
+/// Looks for the best split with all the options of the search given in
+/// `args`.
+fn solve_splits(args: &Args, objective: Objective) -> Option<Solution<u64>> {
+    let token = args.time_limit.map(|limit| {
+        let limit = Duration::try_from_secs_f64(limit).unwrap_or_else(|err| {
+            eprintln!("Invalid time limit: {err}");
+            exit(exitcode::USAGE);
+        });
+        CancelToken::with_time_limit(limit)
+    });
+    let store = args.cache.then(|| open_store(args));
+    let params = AnnealParams {
+        seed: args.seed.unwrap_or_default(),
+        ..Default::default()
+    };
+    let trace_file = args.trace.as_deref().map(|path| {
+        let file = File::create(path).unwrap_or_else(|err| {
+            eprintln!("Cannot create the trace file: {err}");
+            exit(exitcode::CANTCREAT);
+        });
+        // Keep the first error, as later writes are likely to fail too
+        Mutex::new((BufWriter::new(file), Ok(())))
+    });
+    let trace = |event: TraceEvent| {
+        let output = trace_file.as_ref().expect("Only traced with a trace file");
+        let (writer, result): &mut (_, io::Result<()>) = &mut output.lock().unwrap();
+        if result.is_ok() {
+            *result = writeln!(writer, "{}", event.to_json());
+        }
+    };
+    let progress = |progress: &Progress| eprintln!("{progress}");
+
+    let options = SplitOptions {
+        solver: match args.solver {
+            Solver::Local => SplitSolver::Local {
+                store: store.as_ref(),
+            },
+            Solver::Anneal => SplitSolver::Anneal(&params),
+        },
+        token: token.as_ref(),
+        progress: args.progress.then_some(&progress as _),
+        trace: trace_file.is_some().then_some(&trace as _),
+    };
+    let outcome = find_best_split(
+        args.width(),
+        args.height(),
+        args.ncaches.into(),
+        objective,
+        &options,
+    )
+    .unwrap_or_else(|err| {
+        eprintln!("Cannot update the solution store: {err}");
+        exit(exitcode::IOERR);
+    });
+
+    if let Some(output) = trace_file {
+        let (mut writer, result) = output.into_inner().unwrap();
+        if let Err(err) = result.and_then(|_| writer.flush()) {
+            eprintln!("Cannot write the trace file: {err}");
+            exit(exitcode::IOERR);
+        }
+    }
+    let outcome = outcome?;
+    if !outcome.is_final() {
+        eprintln!("Time limit reached. The placement might not be a local optimum.");
+    }
+
+    Some(outcome.into_solution())
+}
+
+fn open_store(args: &Args) -> SolutionStore {
+    let store = match &args.cache_dir {
+        Some(dir) => SolutionStore::open(dir),
+        None => SolutionStore::open_default(),
+    }
+    .and_then(|store| {
+        if args.clear_cache {
+            store.clear()?;
+        }
+        Ok(store)
+    });
+
+    store.unwrap_or_else(|err| {
+        eprintln!("Cannot open the solution store: {err}");
+        exit(exitcode::IOERR);
+    })
+}
+
+fn solve_lattice(args: &Args, depth: u64) -> ! {
+    let Some(sol) =
+        find_best_lattice_split(args.width(), args.height(), depth, args.ncaches.into())
+    else {
+        no_placement()
+    };
+
+    if !args.hide_cost {
+        println!("{}", sol.cost());
+    }
+    if args.show_caches {
+        println!(
+            "{:?}×{:?}×{:?}",
+            sol.x_caches(),
+            sol.y_caches(),
+            sol.z_caches()
+        );
+    }
+
+    exit(exitcode::OK);
+}
+
+fn solve_off_axis(args: &Args) -> ! {
+    let method = if args.exact {
+        Method::Exact
+    } else {
+        Method::LocalSearch
+    };
+    if method == Method::Exact
+        && !is_exact_feasible(args.width(), args.height(), args.ncaches.into())
     {
-        Some((cost, w, h)) => (cost, w, h),
-        _ => (u64::MAX, vec![], vec![]),
+        eprintln!(
+            "The grid is too large to be solved exactly, as there are more than \
+             {MAX_EXACT_PLACEMENTS} placements."
+        );
+        exit(exitcode::USAGE);
+    }
+
+    match compare_with_in_axis(args.width(), args.height(), args.ncaches.into(), method) {
+        Some(comparison) => {
+            if !args.hide_cost {
+                println!(
+                    "{} {} {:.6}",
+                    comparison.off_axis().cost(),
+                    comparison.in_axis_cost(),
+                    comparison.gap()
+                );
+            }
+            if args.show_caches {
+                println!("{:?}", comparison.off_axis().caches());
+            }
+        }
+        None => no_placement(),
+    }
+
+    exit(exitcode::OK);
+}
+
+fn solve_topology(args: &Args, path: &PathBuf) -> ! {
+    let producer = args
+        .producer
+        .as_deref()
+        .expect("Clap requires the producer");
+
+    let sol = match Topology::load(path)
+        .and_then(|topology| get_tree_cache_locations(&topology, producer, args.ncaches.into()))
+    {
+        Ok(sol) => sol,
+        Err(err) => {
+            eprintln!("{err}");
+            exit(exitcode::DATAERR);
+        }
     };
 
     if !args.hide_cost {
-        println!("{best_cost}");
+        println!("{}", sol.cost());
     }
     if args.show_caches {
-        println!("{:?}×{:?}", best_w, best_h);
+        println!("{:?}", sol.caches());
+    }
+
+    exit(exitcode::OK);
+}
+
+fn export(
+    width: u64,
+    height: u64,
+    ncaches: u16,
+    format: ExportFormat,
+    params: &ExportParams,
+    output: Option<&Path>,
+) -> ! {
+    if ncaches == 0 {
+        eprintln!("There must be at least one cache");
+        exit(exitcode::USAGE);
+    }
+    let options = SplitOptions::default();
+    let best = find_best_split(width, height, ncaches.into(), Objective::Nominal, &options)
+        .expect("Solving without a store never fails");
+    let Some(sol) = best.map(Outcome::into_solution) else {
+        no_placement()
+    };
+
+    let result = match (format, output) {
+        (ExportFormat::Ndnsim, Some(path)) => File::create(path).and_then(|file| {
+            let mut writer = BufWriter::new(file);
+            write_ndnsim_topology(&mut writer, width, height, &sol, params)?;
+            writer.flush()
+        }),
+        (ExportFormat::Ndnsim, None) => {
+            write_ndnsim_topology(&mut io::stdout().lock(), width, height, &sol, params)
+        }
+        (ExportFormat::Nfd, dir) => {
+            let dir = dir.expect("Clap requires the output directory for nfd");
+            std::fs::create_dir_all(dir).and_then(|_| {
+                nfd_fragments(width, height, &sol, params).try_for_each(|(name, fragment)| {
+                    std::fs::write(dir.join(format!("{name}.conf")), fragment)
+                })
+            })
+        }
+    };
+
+    if let Err(err) = result {
+        eprintln!("Cannot write the configuration: {err}");
+        exit(exitcode::IOERR);
     }
 
     exit(exitcode::OK);
 }
+
+#[cfg(feature = "server")]
+fn serve(listen: &str, threads: Option<usize>) -> ! {
+    if let Err(err) = fastgridcache::server::serve(listen, threads) {
+        eprintln!("{err}");
+        exit(exitcode::UNAVAILABLE);
+    }
+
+    exit(exitcode::OK);
+}
+
+#[cfg(not(feature = "server"))]
+fn serve(_listen: &str, _threads: Option<usize>) -> ! {
+    eprintln!("This build does not include the server feature.");
+    exit(exitcode::UNAVAILABLE);
+}
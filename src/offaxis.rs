@@ -0,0 +1,428 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+/*
+ *
+ * Copyright (c) 2022 Universidade de Vigo
+ *
+ * This program is free software; you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License version 2 as
+ * published by the Free Software Foundation;
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program; if not, write to the Free Software
+ * Foundation, Inc., 59 Temple Place, Suite 330, Boston, MA  02111-1307  USA
+ *
+ * Author: Miguel Rodríguez Pérez <miguel@det.uvigo.gal>
+ *
+ */
+
+//! Cache placement without the in-axis restriction.
+//!
+//! Caches may be placed in any node of the quadrant. A node is served by the
+//! closest cache in one of its shortest paths towards the producer, that is, by
+//! the closest cache with both coordinates not greater than its own. The
+//! contribution of the caches themselves that [`get_cost`](crate::get_cost)
+//! adds has no counterpart here, so it is left out of all the costs in this
+//! module, including the in-axis ones used for comparison.
+
+use itertools::Itertools;
+use rayon::iter::{IntoParallelIterator, ParallelBridge, ParallelIterator};
+
+use crate::{cost::get_grid_cost, find_best_split, Objective, Solution, SplitOptions};
+
+/// Largest number of placements that [`Method::Exact`] is allowed to evaluate.
+pub const MAX_EXACT_PLACEMENTS: u64 = 10_000_000;
+
+/// Algorithm used to place the caches.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Method {
+    /// Greedy construction followed by a swap local search.
+    #[default]
+    LocalSearch,
+    /// Evaluation of every placement. Only feasible for tiny grids.
+    Exact,
+}
+
+/// Placement of caches anywhere in the grid.
+#[derive(Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct OffAxisSolution {
+    cost: u64,
+    caches: Box<[(u64, u64)]>,
+}
+
+impl OffAxisSolution {
+    /// Get the solution's cost.
+    pub fn cost(&self) -> u64 {
+        self.cost
+    }
+
+    /// Get a reference to the solution's caches as (x, y) coordinates.
+    pub fn caches(&self) -> &[(u64, u64)] {
+        self.caches.as_ref()
+    }
+}
+
+/// Off-axis placement together with the in-axis one for the same budget.
+#[derive(Debug)]
+pub struct Comparison {
+    off_axis: OffAxisSolution,
+    in_axis: Solution<u64>,
+    in_axis_cost: u64,
+}
+
+impl Comparison {
+    /// Get a reference to the off-axis solution.
+    pub fn off_axis(&self) -> &OffAxisSolution {
+        &self.off_axis
+    }
+
+    /// Get a reference to the in-axis solution.
+    pub fn in_axis(&self) -> &Solution<u64> {
+        &self.in_axis
+    }
+
+    /// Get the cost of the in-axis solution, without the contribution of the
+    /// caches.
+    pub fn in_axis_cost(&self) -> u64 {
+        self.in_axis_cost
+    }
+
+    /// Get the fraction of the in-axis cost that is saved by placing the caches
+    /// off the axes.
+    pub fn gap(&self) -> f64 {
+        if self.in_axis_cost == 0 {
+            0.0
+        } else {
+            (self.in_axis_cost - self.off_axis.cost) as f64 / self.in_axis_cost as f64
+        }
+    }
+}
+
+/// Distances of every node in the grid to the producer or to its closest
+/// cache.
+#[derive(Debug, Clone)]
+struct Coverage {
+    width: usize,
+    height: usize,
+    /// For each node, the largest `x + y` of the caches that can serve it.
+    served_by: Vec<u64>,
+}
+
+impl Coverage {
+    fn create(width: u64, height: u64, caches: &[(u64, u64)]) -> Self {
+        let (width, height) = (width as usize, height as usize);
+        let mut served_by = vec![0; width * height];
+
+        for &(x, y) in caches {
+            let i = y as usize * width + x as usize;
+            served_by[i] = served_by[i].max(x + y);
+        }
+        for y in 0..height {
+            for x in 0..width {
+                let left = if x > 0 {
+                    served_by[y * width + x - 1]
+                } else {
+                    0
+                };
+                let below = if y > 0 {
+                    served_by[(y - 1) * width + x]
+                } else {
+                    0
+                };
+                let i = y * width + x;
+                served_by[i] = served_by[i].max(left).max(below);
+            }
+        }
+
+        Self {
+            width,
+            height,
+            served_by,
+        }
+    }
+
+    fn cost(&self) -> u64 {
+        let (w, h) = (self.width as u64, self.height as u64);
+        let distance_to_producer = h * w * (w - 1) / 2 + w * h * (h - 1) / 2;
+
+        distance_to_producer - self.served_by.iter().sum::<u64>()
+    }
+
+    /// Cost reduction obtained by adding a cache at `(x, y)`.
+    fn gain(&self, x: usize, y: usize) -> u64 {
+        let value = (x + y) as u64;
+        let mut gain = 0;
+
+        for row in y..self.height {
+            let row = &self.served_by[row * self.width..(row + 1) * self.width];
+            let mut improved = row[x..]
+                .iter()
+                .take_while(|&&served_by| served_by < value)
+                .peekable();
+            if improved.peek().is_none() {
+                break;
+            }
+            gain += improved.map(|served_by| value - served_by).sum::<u64>();
+        }
+
+        gain
+    }
+
+    /// Node with the largest gain, if any cache improves the cost.
+    fn best_addition(&self) -> Option<((u64, u64), u64)> {
+        (1..self.width * self.height) // Skip the producer
+            .into_par_iter()
+            .map(|i| {
+                let (x, y) = (i % self.width, i / self.width);
+                ((x as u64, y as u64), self.gain(x, y))
+            })
+            .filter(|&(_, gain)| gain > 0)
+            .max_by_key(|&(pos, gain)| (gain, std::cmp::Reverse(pos)))
+    }
+}
+
+fn greedy(width: u64, height: u64, ncaches: usize) -> Vec<(u64, u64)> {
+    let mut caches = Vec::with_capacity(ncaches);
+
+    while caches.len() < ncaches {
+        match Coverage::create(width, height, &caches).best_addition() {
+            Some((pos, _)) => caches.push(pos),
+            None => break,
+        }
+    }
+
+    caches
+}
+
+/// Moves caches to any other node while that reduces the cost.
+fn swap_search(width: u64, height: u64, mut caches: Vec<(u64, u64)>) -> OffAxisSolution {
+    let mut cost = Coverage::create(width, height, &caches).cost();
+
+    let mut improves = true;
+    while improves {
+        improves = false;
+        for i in 0..caches.len() {
+            let removed = caches.swap_remove(i);
+            let coverage = Coverage::create(width, height, &caches);
+            let best = coverage
+                .best_addition()
+                .filter(|&(_, gain)| coverage.cost() - gain < cost);
+
+            match best {
+                Some((pos, gain)) => {
+                    caches.push(pos);
+                    cost = coverage.cost() - gain;
+                    improves = true;
+                }
+                None => caches.push(removed),
+            }
+            let last = caches.len() - 1;
+            caches.swap(i, last);
+        }
+    }
+
+    caches.sort_unstable();
+    OffAxisSolution {
+        cost,
+        caches: caches.into_boxed_slice(),
+    }
+}
+
+fn exact(width: u64, height: u64, ncaches: usize) -> OffAxisSolution {
+    let nodes = (width * height).saturating_sub(1) as usize;
+    let position = |i: usize| (i as u64 % width, i as u64 / width);
+
+    // Stream the placements instead of holding all of them at once. They are
+    // evaluated in any order, so ties go to the first one explicitly
+    let (cost, nodes) = (1..=nodes)
+        .combinations(ncaches.min(nodes))
+        .par_bridge()
+        .map(|nodes| {
+            let caches: Vec<_> = nodes.iter().map(|&i| position(i)).collect();
+            (Coverage::create(width, height, &caches).cost(), nodes)
+        })
+        .min()
+        .expect("there is always a placement");
+
+    OffAxisSolution {
+        cost,
+        caches: nodes.into_iter().map(position).collect(),
+    }
+}
+
+/// Number of placements of `ncaches` caches in `nodes` nodes, or `None` if it
+/// does not fit in a `u64`.
+fn count_placements(nodes: usize, ncaches: usize) -> Option<u64> {
+    (0..ncaches.min(nodes) as u64).try_fold(1u64, |count, i| {
+        Some(count.checked_mul(nodes as u64 - i)? / (i + 1))
+    })
+}
+
+/// Whether [`Method::Exact`] can place `ncaches` caches in a `width`×`height`
+/// grid evaluating at most [`MAX_EXACT_PLACEMENTS`] placements.
+pub fn is_exact_feasible(width: u64, height: u64, ncaches: usize) -> bool {
+    let nodes = (width * height).saturating_sub(1) as usize;
+
+    count_placements(nodes, ncaches).is_some_and(|count| count <= MAX_EXACT_PLACEMENTS)
+}
+
+/// Places `ncaches` caches anywhere in a `width`×`height` grid.
+///
+/// Returns `None` if `method` is [`Method::Exact`] and the grid is too large
+/// for it, see [`is_exact_feasible`].
+pub fn get_off_axis_cache_locations(
+    width: u64,
+    height: u64,
+    ncaches: usize,
+    method: Method,
+) -> Option<OffAxisSolution> {
+    match method {
+        Method::LocalSearch => Some(swap_search(width, height, greedy(width, height, ncaches))),
+        Method::Exact => {
+            is_exact_feasible(width, height, ncaches).then(|| exact(width, height, ncaches))
+        }
+    }
+}
+
+/// Places `ncaches` caches anywhere in a `width`×`height` grid and compares
+/// the result with the best in-axis placement.
+///
+/// The local search also starts from the in-axis placement, so the off-axis
+/// cost is never worse than the in-axis one. Without caches, both placements
+/// are empty. Returns `None` if there is no valid in-axis placement for the
+/// grid, or if `method` is [`Method::Exact`] and the grid is too large for it,
+/// which is checked before any search.
+pub fn compare_with_in_axis(
+    width: u64,
+    height: u64,
+    ncaches: usize,
+    method: Method,
+) -> Option<Comparison> {
+    if method == Method::Exact && !is_exact_feasible(width, height, ncaches) {
+        return None;
+    }
+
+    let in_axis = if ncaches == 0 {
+        Solution::without_caches(get_grid_cost(width, height, &[], &[]))
+    } else {
+        find_best_split(
+            width,
+            height,
+            ncaches,
+            Objective::Nominal,
+            &SplitOptions::default(),
+        )
+        .expect("Solving without a store never fails")?
+        .into_solution()
+    };
+    let in_axis_caches: Vec<_> = in_axis
+        .horizontal_caches()
+        .iter()
+        .map(|&x| (x, 0))
+        .chain(in_axis.vertical_caches().iter().map(|&y| (0, y)))
+        .filter(|&(x, y)| x < width && y < height)
+        .collect();
+    let in_axis_cost = Coverage::create(width, height, &in_axis_caches).cost();
+
+    let off_axis = match method {
+        Method::LocalSearch => {
+            let (from_greedy, from_in_axis) = rayon::join(
+                || swap_search(width, height, greedy(width, height, ncaches)),
+                || swap_search(width, height, in_axis_caches),
+            );
+            std::cmp::min_by_key(from_greedy, from_in_axis, |sol| sol.cost)
+        }
+        Method::Exact => exact(width, height, ncaches),
+    };
+
+    Some(Comparison {
+        off_axis,
+        in_axis,
+        in_axis_cost,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{cost::get_grid_cost, get_cache_locations};
+
+    use super::{
+        compare_with_in_axis, count_placements, get_off_axis_cache_locations, is_exact_feasible,
+        Coverage, Method,
+    };
+
+    #[test]
+    fn in_axis_coverage_matches_grid_cost() {
+        let sol = get_cache_locations(100, 60, 3, 1);
+        let caches: Vec<_> = sol
+            .horizontal_caches()
+            .iter()
+            .map(|&x| (x, 0))
+            .chain(sol.vertical_caches().iter().map(|&y| (0, y)))
+            .collect();
+
+        let cache_costs =
+            sol.horizontal_caches().last().unwrap() + sol.vertical_caches().last().unwrap();
+
+        assert_eq!(
+            Coverage::create(100, 60, &caches).cost(),
+            sol.cost() - cache_costs
+        );
+        assert_eq!(
+            Coverage::create(100, 60, &[]).cost(),
+            get_grid_cost(100, 60, &[], &[])
+        );
+    }
+
+    #[test]
+    fn gain_is_cost_reduction() {
+        let coverage = Coverage::create(20, 10, &[(5, 0), (3, 4)]);
+        let with_cache = Coverage::create(20, 10, &[(5, 0), (3, 4), (8, 2)]);
+
+        assert_eq!(coverage.cost() - coverage.gain(8, 2), with_cache.cost());
+    }
+
+    #[test]
+    fn local_search_finds_exact_optimum() {
+        let exact = get_off_axis_cache_locations(6, 5, 2, Method::Exact).unwrap();
+        let local = get_off_axis_cache_locations(6, 5, 2, Method::LocalSearch).unwrap();
+
+        assert_eq!(local.cost(), exact.cost());
+    }
+
+    #[test]
+    fn off_axis_never_worse() {
+        let comparison = compare_with_in_axis(40, 30, 3, Method::LocalSearch).unwrap();
+
+        assert!(comparison.off_axis().cost() <= comparison.in_axis_cost());
+        assert!(comparison.gap() >= 0.0);
+    }
+
+    #[test]
+    fn without_caches() {
+        for method in [Method::LocalSearch, Method::Exact] {
+            let comparison = compare_with_in_axis(6, 5, 0, method).unwrap();
+
+            assert!(comparison.off_axis().caches().is_empty());
+            assert_eq!(comparison.off_axis().cost(), get_grid_cost(6, 5, &[], &[]));
+            assert_eq!(comparison.in_axis_cost(), comparison.off_axis().cost());
+            assert_eq!(comparison.gap(), 0.0);
+        }
+    }
+
+    #[test]
+    fn exact_rejects_large_grids() {
+        assert_eq!(count_placements(29, 2), Some(406));
+        assert_eq!(count_placements(1 << 20, 8), None);
+        assert!(is_exact_feasible(6, 5, 2));
+        assert!(!is_exact_feasible(100, 100, 3));
+        assert!(get_off_axis_cache_locations(100, 100, 3, Method::Exact).is_none());
+        assert!(compare_with_in_axis(100, 100, 3, Method::Exact).is_none());
+    }
+}
@@ -0,0 +1,101 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+/*
+ *
+ * Copyright (c) 2022 Universidade de Vigo
+ *
+ * This program is free software; you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License version 2 as
+ * published by the Free Software Foundation;
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program; if not, write to the Free Software
+ * Foundation, Inc., 59 Temple Place, Suite 330, Boston, MA  02111-1307  USA
+ *
+ * Author: Miguel Rodríguez Pérez <miguel@det.uvigo.gal>
+ *
+ */
+
+//! Control of the threads used by the searches.
+//!
+//! Every search runs its parallel parts in the current rayon pool, so running
+//! it through [`Parallelism::install`] is enough to choose its threads.
+
+use rayon::{ThreadPool, ThreadPoolBuildError, ThreadPoolBuilder};
+
+/// Threads where the parallel parts of a search run.
+#[derive(Debug, Clone, Copy, Default)]
+pub enum Parallelism<'a> {
+    /// Rayon's global pool, shared by the whole process.
+    #[default]
+    Global,
+    /// The given pool.
+    Pool(&'a ThreadPool),
+    /// A new pool with this number of threads.
+    Threads(usize),
+    /// A single thread, so that runs are deterministic and easy to profile.
+    Sequential,
+}
+
+impl Parallelism<'_> {
+    /// Runs `op`, and the parallel searches inside it, in the selected
+    /// threads. It only fails if a new pool cannot be built.
+    pub fn install<R, F>(&self, op: F) -> Result<R, ThreadPoolBuildError>
+    where
+        R: Send,
+        F: FnOnce() -> R + Send,
+    {
+        match self {
+            Parallelism::Global => Ok(op()),
+            Parallelism::Pool(pool) => Ok(pool.install(op)),
+            Parallelism::Threads(threads) => build_pool(*threads).map(|pool| pool.install(op)),
+            Parallelism::Sequential => build_pool(1).map(|pool| pool.install(op)),
+        }
+    }
+}
+
+fn build_pool(threads: usize) -> Result<ThreadPool, ThreadPoolBuildError> {
+    ThreadPoolBuilder::new().num_threads(threads).build()
+}
+
+#[cfg(test)]
+mod tests {
+    use rayon::ThreadPoolBuilder;
+
+    use crate::{cancel::Outcome, find_best_split, Objective, SplitOptions};
+
+    use super::Parallelism;
+
+    #[test]
+    fn same_solution_in_every_pool() {
+        let solve = || {
+            find_best_split(100, 80, 4, Objective::Nominal, &SplitOptions::default())
+                .unwrap()
+                .map(Outcome::into_solution)
+        };
+        let expected = solve();
+        let pool = ThreadPoolBuilder::new().num_threads(2).build().unwrap();
+
+        for parallelism in [
+            Parallelism::Global,
+            Parallelism::Pool(&pool),
+            Parallelism::Threads(3),
+            Parallelism::Sequential,
+        ] {
+            assert_eq!(parallelism.install(solve).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn sequential_uses_one_thread() {
+        let threads = Parallelism::Sequential
+            .install(rayon::current_num_threads)
+            .unwrap();
+
+        assert_eq!(threads, 1);
+    }
+}
@@ -0,0 +1,179 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+/*
+ *
+ * Copyright (c) 2022 Universidade de Vigo
+ *
+ * This program is free software; you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License version 2 as
+ * published by the Free Software Foundation;
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program; if not, write to the Free Software
+ * Foundation, Inc., 59 Temple Place, Suite 330, Boston, MA  02111-1307  USA
+ *
+ * Author: Miguel Rodríguez Pérez <miguel@det.uvigo.gal>
+ *
+ */
+
+use std::{error::Error, fmt::Display};
+
+use crate::{gridsearcher::get_objective_cache_locations, Objective, Solution};
+
+/// Largest number of failures of a worst-case objective. Its cost grows
+/// exponentially with them.
+pub const MAX_FAILURES: usize = 4;
+
+/// Reasons why a problem cannot be solved.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProblemError {
+    /// There are no caches at all.
+    NoCaches,
+    /// The caches of an axis do not fit in the grid.
+    TooManyCaches,
+    /// The objective allows more than [`MAX_FAILURES`] failures.
+    TooManyFailures,
+}
+
+impl Display for ProblemError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ProblemError::NoCaches => write!(f, "There are no caches"),
+            ProblemError::TooManyCaches => write!(f, "Caches do not fit in the grid"),
+            ProblemError::TooManyFailures => {
+                write!(f, "At most {MAX_FAILURES} failures are supported")
+            }
+        }
+    }
+}
+
+impl Error for ProblemError {}
+
+/// Description of a placement problem in a grid, so that it can be stored
+/// or sent along with its solution.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ProblemDescription {
+    pub width: u64,
+    pub height: u64,
+    pub nhoriz: usize,
+    pub nvert: usize,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub objective: Objective,
+}
+
+impl ProblemDescription {
+    /// Checks that there are caches, that they fit in the grid and that the
+    /// objective is supported.
+    pub fn validate(&self) -> Result<(), ProblemError> {
+        match self.nhoriz.checked_add(self.nvert) {
+            Some(0) => return Err(ProblemError::NoCaches),
+            None => return Err(ProblemError::TooManyCaches),
+            Some(_) => (),
+        }
+        if self.width <= self.nhoriz as u64 || self.height <= self.nvert as u64 {
+            return Err(ProblemError::TooManyCaches);
+        }
+
+        self.objective.validate()
+    }
+
+    /// Looks for the placement of the caches of the problem.
+    pub fn solve(&self) -> Solution<u64> {
+        get_objective_cache_locations(
+            self.width,
+            self.height,
+            self.nhoriz,
+            self.nvert,
+            self.objective,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Objective;
+    #[cfg(feature = "serde")]
+    use crate::Solution;
+
+    use super::{ProblemDescription, ProblemError};
+
+    #[test]
+    fn validate() {
+        let problem = |nhoriz, nvert, failures| ProblemDescription {
+            width: 100,
+            height: 60,
+            nhoriz,
+            nvert,
+            objective: Objective::from_failures(failures),
+        };
+
+        assert_eq!(problem(3, 1, 4).validate(), Ok(()));
+        assert_eq!(problem(0, 0, 0).validate(), Err(ProblemError::NoCaches));
+        assert_eq!(
+            problem(3, 60, 0).validate(),
+            Err(ProblemError::TooManyCaches)
+        );
+        assert_eq!(
+            problem(usize::MAX, 1, 0).validate(),
+            Err(ProblemError::TooManyCaches)
+        );
+        assert_eq!(
+            problem(3, 1, 5).validate(),
+            Err(ProblemError::TooManyFailures)
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn problem_round_trip() {
+        let problem = ProblemDescription {
+            width: 100,
+            height: 60,
+            nhoriz: 3,
+            nvert: 1,
+            objective: Objective::WorstCase { failures: 1 },
+        };
+        let json = serde_json::to_string(&problem).unwrap();
+
+        assert_eq!(
+            serde_json::from_str::<ProblemDescription>(&json).unwrap(),
+            problem
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn default_objective() {
+        let problem: ProblemDescription =
+            serde_json::from_str(r#"{"width": 100, "height": 80, "nhoriz": 2, "nvert": 2}"#)
+                .unwrap();
+
+        assert_eq!(problem.objective, Objective::Nominal);
+        assert_eq!(problem.solve().cost(), 350_224);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn solution_round_trip() {
+        let problem = ProblemDescription {
+            width: 100,
+            height: 50,
+            nhoriz: 3,
+            nvert: 1,
+            objective: Objective::Nominal,
+        };
+        let sol = problem.solve();
+        let json = serde_json::to_string(&sol).unwrap();
+
+        assert_eq!(
+            json,
+            r#"{"cost":164053,"vertical_caches":[25],"horizontal_caches":[34,56,78]}"#
+        );
+        assert_eq!(serde_json::from_str::<Solution<u64>>(&json).unwrap(), sol);
+    }
+}
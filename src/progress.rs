@@ -0,0 +1,149 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+/*
+ *
+ * Copyright (c) 2022 Universidade de Vigo
+ *
+ * This program is free software; you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License version 2 as
+ * published by the Free Software Foundation;
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program; if not, write to the Free Software
+ * Foundation, Inc., 59 Temple Place, Suite 330, Boston, MA  02111-1307  USA
+ *
+ * Author: Miguel Rodríguez Pérez <miguel@det.uvigo.gal>
+ *
+ */
+
+//! Reports of how far long searches have got.
+
+use std::{
+    fmt::Display,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// State of a search made of several subproblems.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Progress {
+    /// Subproblems solved.
+    pub completed: usize,
+    /// Subproblems in the search.
+    pub total: usize,
+    /// Cost of the best solution found so far.
+    pub best_cost: Option<u64>,
+    /// Time since the search started.
+    pub elapsed: Duration,
+}
+
+impl Progress {
+    /// Estimated time until the search finishes, assuming the remaining
+    /// subproblems take as long as the completed ones.
+    pub fn eta(&self) -> Option<Duration> {
+        if self.completed == 0 {
+            return None;
+        }
+
+        let per_problem = self.elapsed / self.completed as u32;
+        Some(per_problem * (self.total - self.completed) as u32)
+    }
+}
+
+impl Display for Progress {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[{}/{}]", self.completed, self.total)?;
+        if let Some(cost) = self.best_cost {
+            write!(f, " best cost {cost}")?;
+        }
+        write!(f, ", elapsed {:.1}s", self.elapsed.as_secs_f64())?;
+        if let Some(eta) = self.eta() {
+            write!(f, ", ETA {:.1}s", eta.as_secs_f64())?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Collects the subproblems solved, from any thread, and reports the progress
+/// after each one.
+pub(crate) struct ProgressTracker<'a, F: ?Sized> {
+    total: usize,
+    start: Instant,
+    state: Mutex<(usize, Option<u64>)>,
+    callback: &'a F,
+}
+
+impl<'a, F> ProgressTracker<'a, F>
+where
+    F: Fn(&Progress) + Sync + ?Sized,
+{
+    pub(crate) fn create(total: usize, callback: &'a F) -> Self {
+        Self {
+            total,
+            start: Instant::now(),
+            state: Mutex::new((0, None)),
+            callback,
+        }
+    }
+
+    /// Records that a subproblem has been solved with a solution of `cost`.
+    pub(crate) fn record(&self, cost: u64) {
+        let mut state = self.state.lock().unwrap();
+        let (completed, best_cost) = &mut *state;
+        *completed += 1;
+        *best_cost = Some(best_cost.map_or(cost, |best| best.min(cost)));
+
+        // Report while locked, so reports arrive in order
+        (self.callback)(&Progress {
+            completed: *completed,
+            total: self.total,
+            best_cost: *best_cost,
+            elapsed: self.start.elapsed(),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Mutex, time::Duration};
+
+    use super::{Progress, ProgressTracker};
+
+    #[test]
+    fn eta_from_completed() {
+        let progress = Progress {
+            completed: 2,
+            total: 6,
+            best_cost: Some(100),
+            elapsed: Duration::from_secs(4),
+        };
+
+        assert_eq!(progress.eta(), Some(Duration::from_secs(8)));
+        assert_eq!(
+            progress.to_string(),
+            "[2/6] best cost 100, elapsed 4.0s, ETA 8.0s"
+        );
+    }
+
+    #[test]
+    fn tracker_keeps_best() {
+        let reports = Mutex::new(Vec::new());
+        let callback = |progress: &Progress| reports.lock().unwrap().push(*progress);
+        let tracker = ProgressTracker::create(3, &callback);
+        tracker.record(30);
+        tracker.record(10);
+        tracker.record(20);
+
+        let reports = reports.into_inner().unwrap();
+        assert_eq!(
+            reports.iter().map(|p| p.best_cost).collect::<Vec<_>>(),
+            [Some(30), Some(10), Some(10)]
+        );
+        assert_eq!(reports[2].eta(), Some(Duration::ZERO));
+    }
+}
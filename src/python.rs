@@ -0,0 +1,178 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+/*
+ *
+ * Copyright (c) 2022 Universidade de Vigo
+ *
+ * This program is free software; you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License version 2 as
+ * published by the Free Software Foundation;
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program; if not, write to the Free Software
+ * Foundation, Inc., 59 Temple Place, Suite 330, Boston, MA  02111-1307  USA
+ *
+ * Author: Miguel Rodríguez Pérez <miguel@det.uvigo.gal>
+ *
+ */
+
+//! Python bindings, built as an extension module with the `python` feature.
+//!
+//! The solvers release the GIL while they run, so other Python threads can
+//! progress during the rayon-parallel searches.
+
+use numpy::{AllowTypeChange, PyArrayLike2};
+use pyo3::{exceptions::PyValueError, prelude::*};
+
+use crate::{demand::Demand, Objective, ProblemDescription, ProblemError, Solution};
+
+/// Placement of caches in the horizontal and vertical axes.
+#[pyclass(name = "Solution", module = "fastgridcache", frozen)]
+struct PySolution(Solution<u64>);
+
+#[pymethods]
+impl PySolution {
+    #[getter]
+    fn cost(&self) -> u64 {
+        self.0.cost()
+    }
+
+    #[getter]
+    fn horizontal_caches(&self) -> Vec<u64> {
+        self.0.horizontal_caches().to_vec()
+    }
+
+    #[getter]
+    fn vertical_caches(&self) -> Vec<u64> {
+        self.0.vertical_caches().to_vec()
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "Solution(cost={}, horizontal_caches={:?}, vertical_caches={:?})",
+            self.0.cost(),
+            self.0.horizontal_caches(),
+            self.0.vertical_caches()
+        )
+    }
+}
+
+fn value_error(err: ProblemError) -> PyErr {
+    PyValueError::new_err(err.to_string())
+}
+
+/// Looks for the placement of `nhoriz` horizontal and `nvert` vertical caches.
+#[pyfunction]
+#[pyo3(signature = (width, height, nhoriz, nvert, failures = 0))]
+fn get_cache_locations(
+    py: Python<'_>,
+    width: u64,
+    height: u64,
+    nhoriz: usize,
+    nvert: usize,
+    failures: usize,
+) -> PyResult<PySolution> {
+    let problem = ProblemDescription {
+        width,
+        height,
+        nhoriz,
+        nvert,
+        objective: Objective::from_failures(failures),
+    };
+    problem.validate().map_err(value_error)?;
+
+    let sol = py.detach(|| problem.solve());
+
+    Ok(PySolution(sol))
+}
+
+/// Looks for the best split of `ncaches` between both axes and its placement.
+#[pyfunction]
+#[pyo3(signature = (width, height, ncaches, failures = 0))]
+fn find_best_split(
+    py: Python<'_>,
+    width: u64,
+    height: u64,
+    ncaches: usize,
+    failures: usize,
+) -> PyResult<Option<PySolution>> {
+    if ncaches == 0 {
+        return Err(value_error(ProblemError::NoCaches));
+    }
+    let objective = Objective::from_failures(failures);
+    objective.validate().map_err(value_error)?;
+
+    Ok(py
+        .detach(|| {
+            crate::find_best_split(
+                width,
+                height,
+                ncaches,
+                objective,
+                &crate::SplitOptions::default(),
+            )
+        })
+        .expect("Solving without a store never fails")
+        .map(|outcome| PySolution(outcome.into_solution())))
+}
+
+/// Looks for the best split of `ncaches` between both axes and its placement,
+/// weighting the hops of every node by `demand`, a matrix of non-negative
+/// integers with a row for every `y` and a column for every `x`.
+#[pyfunction]
+fn find_best_weighted_split(
+    py: Python<'_>,
+    demand: PyArrayLike2<'_, i64, AllowTypeChange>,
+    ncaches: usize,
+) -> PyResult<Option<PySolution>> {
+    if ncaches == 0 {
+        return Err(value_error(ProblemError::NoCaches));
+    }
+
+    let demand = demand.as_array();
+    let (height, width) = demand.dim();
+    let weights = demand
+        .iter()
+        .map(|&weight| u64::try_from(weight))
+        .collect::<Result<_, _>>()
+        .map_err(|_| PyValueError::new_err("Demands cannot be negative"))?;
+    let demand = Demand::from_weights(width as u64, height as u64, weights)
+        .expect("There is a weight for every node");
+
+    Ok(py
+        .detach(|| crate::find_best_weighted_split(&demand, ncaches))
+        .map(PySolution))
+}
+
+/// Computes the cost of a placement. Both lists must end with the grid size.
+#[pyfunction]
+fn get_cost(w: Vec<u64>, h: Vec<u64>) -> PyResult<u64> {
+    if w.is_empty() || h.is_empty() {
+        return Err(PyValueError::new_err("Lists cannot be empty"));
+    }
+    let increasing =
+        |caches: &[u64]| caches[0] > 0 && caches.windows(2).all(|pair| pair[0] < pair[1]);
+    if !increasing(&w) || !increasing(&h) {
+        return Err(PyValueError::new_err(
+            "Lists must be strictly increasing and outside the producer",
+        ));
+    }
+
+    Ok(crate::get_cost(&w, &h))
+}
+
+#[pymodule]
+#[pyo3(name = "fastgridcache")]
+fn python_module(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PySolution>()?;
+    m.add_function(wrap_pyfunction!(get_cache_locations, m)?)?;
+    m.add_function(wrap_pyfunction!(find_best_split, m)?)?;
+    m.add_function(wrap_pyfunction!(find_best_weighted_split, m)?)?;
+    m.add_function(wrap_pyfunction!(get_cost, m)?)?;
+
+    Ok(())
+}
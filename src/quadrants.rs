@@ -0,0 +1,372 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+/*
+ *
+ * Copyright (c) 2022 Universidade de Vigo
+ *
+ * This program is free software; you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License version 2 as
+ * published by the Free Software Foundation;
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program; if not, write to the Free Software
+ * Foundation, Inc., 59 Temple Place, Suite 330, Boston, MA  02111-1307  USA
+ *
+ * Author: Miguel Rodríguez Pérez <miguel@det.uvigo.gal>
+ *
+ */
+
+//! Placement with a different cache list in every half-axis.
+//!
+//! The grid is split by the axes of the producer into four quadrants, each one
+//! served by the caches of its two half-axes. Every half-axis has its own
+//! length and budget, so the producer does not need to be at the centre of
+//! the grid. As in the symmetric model, every quadrant includes its two
+//! half-axes and is priced like [`get_cost`](crate::get_cost), so the cost of
+//! a symmetric placement is four times the cost of one quadrant.
+
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+
+use crate::{cost::get_grid_cost, get_cache_locations};
+
+/// Something for each of the four half-axes that start at the producer.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HalfAxes<T> {
+    pub pos_x: T,
+    pub neg_x: T,
+    pub pos_y: T,
+    pub neg_y: T,
+}
+
+impl<T> HalfAxes<T> {
+    fn into_array(self) -> [T; 4] {
+        [self.pos_x, self.neg_x, self.pos_y, self.neg_y]
+    }
+
+    fn from_array([pos_x, neg_x, pos_y, neg_y]: [T; 4]) -> Self {
+        Self {
+            pos_x,
+            neg_x,
+            pos_y,
+            neg_y,
+        }
+    }
+}
+
+/// Half-axes in the x and y directions of each quadrant, as indices of
+/// [`HalfAxes::into_array`].
+const QUADRANTS: [(usize, usize); 4] = [(0, 2), (1, 2), (1, 3), (0, 3)];
+
+/// Placement of the caches in the four half-axes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct QuadrantSolution {
+    cost: u64,
+    caches: HalfAxes<Box<[u64]>>,
+}
+
+impl QuadrantSolution {
+    /// Get the solution's cost.
+    pub fn cost(&self) -> u64 {
+        self.cost
+    }
+
+    /// Get a reference to the positions of the caches in every half-axis,
+    /// as distances to the producer.
+    pub fn caches(&self) -> &HalfAxes<Box<[u64]>> {
+        &self.caches
+    }
+
+    /// Number of caches in every half-axis.
+    pub fn budgets(&self) -> HalfAxes<usize> {
+        HalfAxes {
+            pos_x: self.caches.pos_x.len(),
+            neg_x: self.caches.neg_x.len(),
+            pos_y: self.caches.pos_y.len(),
+            neg_y: self.caches.neg_y.len(),
+        }
+    }
+}
+
+/// Lengths and caches of the half-axes while searching.
+struct Placement {
+    lens: [u64; 4],
+    caches: [Vec<u64>; 4],
+}
+
+impl Placement {
+    /// Every half-axis starts with the placement of the grid searchers in the
+    /// first of its quadrants, or with evenly spread caches if that quadrant
+    /// is empty. A symmetric problem thus starts from the best placement of a
+    /// single quadrant.
+    fn initial(lens: [u64; 4], budgets: [usize; 4]) -> Self {
+        let mut caches: [Vec<u64>; 4] = [0, 1, 2, 3].map(|axis| {
+            let parts = budgets[axis] as u64 + 1;
+            (1..parts).map(|i| i * lens[axis] / parts).collect()
+        });
+
+        let mut placed = [false; 4];
+        for (x, y) in QUADRANTS {
+            if placed[x] && placed[y] {
+                continue;
+            }
+            if lens[x] == 0 || lens[y] == 0 || budgets[x] + budgets[y] == 0 {
+                continue;
+            }
+
+            let sol = get_cache_locations(lens[x], lens[y], budgets[x], budgets[y]);
+            for (axis, found) in [(x, sol.horizontal_caches()), (y, sol.vertical_caches())] {
+                if !placed[axis] {
+                    caches[axis] = found.to_vec();
+                    placed[axis] = true;
+                }
+            }
+        }
+
+        Self { lens, caches }
+    }
+
+    fn quadrant_cost(&self, (x, y): (usize, usize)) -> u64 {
+        get_grid_cost(self.lens[x], self.lens[y], &self.caches[x], &self.caches[y])
+    }
+
+    fn cost(&self) -> u64 {
+        QUADRANTS.iter().map(|&q| self.quadrant_cost(q)).sum()
+    }
+
+    /// Cost of the quadrants served by `axis`.
+    fn axis_cost(&self, axis: usize) -> u64 {
+        QUADRANTS
+            .iter()
+            .filter(|&&(x, y)| x == axis || y == axis)
+            .map(|&q| self.quadrant_cost(q))
+            .sum()
+    }
+
+    /// New position of the cache at `index` of `axis` after moving it by
+    /// `step`, if it stays between its neighbours.
+    fn moved(&self, axis: usize, index: usize, step: u64, forward: bool) -> Option<u64> {
+        let caches = &self.caches[axis];
+        let lower = if index == 0 { 0 } else { caches[index - 1] };
+        let upper = caches.get(index + 1).copied().unwrap_or(self.lens[axis]);
+
+        let pos = if forward {
+            caches[index] + step
+        } else {
+            caches[index].checked_sub(step)?
+        };
+        (lower < pos && pos < upper).then_some(pos)
+    }
+
+    /// Local search with the same moves as the grid searchers: every cache
+    /// moves both ways while it improves, and the steps are halved when no
+    /// cache can move.
+    fn optimise(&mut self) {
+        let total: usize = self.caches.iter().map(Vec::len).sum();
+        let longest = self.lens.into_iter().max().unwrap_or(0);
+        let mut step = (longest / (4 * (total as u64 + 1))).max(1);
+
+        loop {
+            let mut improves = false;
+            for axis in 0..4 {
+                for index in (0..self.caches[axis].len()).rev() {
+                    for forward in [true, false] {
+                        while let Some(pos) = self.moved(axis, index, step, forward) {
+                            let prev = self.caches[axis][index];
+                            let cost = self.axis_cost(axis);
+                            self.caches[axis][index] = pos;
+                            if self.axis_cost(axis) >= cost {
+                                self.caches[axis][index] = prev;
+                                break;
+                            }
+                            improves = true;
+                        }
+                    }
+                }
+            }
+
+            if !improves {
+                if step == 1 {
+                    break;
+                }
+                step /= 2;
+            }
+        }
+    }
+
+    fn into_solution(self) -> QuadrantSolution {
+        QuadrantSolution {
+            cost: self.cost(),
+            caches: HalfAxes::from_array(self.caches.map(Vec::into_boxed_slice)),
+        }
+    }
+}
+
+fn solve(lens: [u64; 4], budgets: [usize; 4]) -> QuadrantSolution {
+    let mut placement = Placement::initial(lens, budgets);
+    placement.optimise();
+
+    placement.into_solution()
+}
+
+/// Computes the cost of the caches in every half-axis, sorted by their
+/// distance to the producer, when the half-axes have the lengths in `extents`.
+pub fn get_quadrant_cost<C: AsRef<[u64]>>(extents: &HalfAxes<u64>, caches: &HalfAxes<C>) -> u64 {
+    let placement = Placement {
+        lens: extents.into_array(),
+        caches: [&caches.pos_x, &caches.neg_x, &caches.pos_y, &caches.neg_y]
+            .map(|caches| caches.as_ref().to_vec()),
+    };
+
+    placement.cost()
+}
+
+/// Looks for the placement of `budgets` caches in the half-axes with the
+/// lengths in `extents`, optimising all of them jointly.
+///
+/// Returns `None` if the caches of a half-axis do not fit in it.
+pub fn get_quadrant_cache_locations(
+    extents: HalfAxes<u64>,
+    budgets: HalfAxes<usize>,
+) -> Option<QuadrantSolution> {
+    let lens = extents.into_array();
+    let budgets = budgets.into_array();
+    if (0..4).any(|axis| budgets[axis] as u64 >= lens[axis].max(1)) {
+        return None;
+    }
+
+    Some(solve(lens, budgets))
+}
+
+/// Looks for the best way of allocating `ncaches` among the half-axes with
+/// the lengths in `extents`, and for the placement of the caches.
+///
+/// Caches are added one at a time to the half-axis where they reduce the cost
+/// the most. Then, caches are transferred between half-axes while that reduces
+/// the cost. Returns `None` if the caches do not fit in the half-axes.
+pub fn find_best_quadrant_split(
+    extents: HalfAxes<u64>,
+    ncaches: usize,
+) -> Option<QuadrantSolution> {
+    let lens = extents.into_array();
+    let capacity: u64 = lens.iter().map(|len| len.saturating_sub(1)).sum();
+    if ncaches as u64 > capacity {
+        return None;
+    }
+
+    let fits = |budgets: &[usize; 4]| (0..4).all(|axis| (budgets[axis] as u64) < lens[axis].max(1));
+    let best_of = |candidates: Vec<[usize; 4]>| {
+        candidates
+            .into_par_iter()
+            .filter(fits)
+            .map(|budgets| (solve(lens, budgets), budgets))
+            .min_by_key(|(sol, _)| sol.cost())
+    };
+
+    let mut budgets = [0; 4];
+    let mut best = solve(lens, budgets);
+    for _ in 0..ncaches {
+        let candidates = (0..4)
+            .map(|axis| {
+                let mut budgets = budgets;
+                budgets[axis] += 1;
+                budgets
+            })
+            .collect();
+        (best, budgets) = best_of(candidates).expect("There is room for another cache");
+    }
+
+    let transfers = |budgets: [usize; 4]| {
+        (0..4)
+            .flat_map(|from| (0..4).map(move |to| (from, to)))
+            .filter(|&(from, to)| from != to && budgets[from] > 0)
+            .map(|(from, to)| {
+                let mut budgets = budgets;
+                budgets[from] -= 1;
+                budgets[to] += 1;
+                budgets
+            })
+            .collect()
+    };
+    while let Some((sol, moved)) = best_of(transfers(budgets)) {
+        if sol.cost() >= best.cost() {
+            break;
+        }
+        (best, budgets) = (sol, moved);
+    }
+
+    Some(best)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{get_cache_locations, get_cost};
+
+    use super::{
+        find_best_quadrant_split, get_quadrant_cache_locations, get_quadrant_cost, HalfAxes,
+    };
+
+    fn symmetric<T: Copy>(x: T, y: T) -> HalfAxes<T> {
+        HalfAxes {
+            pos_x: x,
+            neg_x: x,
+            pos_y: y,
+            neg_y: y,
+        }
+    }
+
+    #[test]
+    fn symmetric_cost() {
+        let caches = HalfAxes {
+            pos_x: vec![34, 56, 78],
+            neg_x: vec![34, 56, 78],
+            pos_y: vec![25],
+            neg_y: vec![25],
+        };
+
+        assert_eq!(
+            get_quadrant_cost(&symmetric(100, 50), &caches),
+            4 * get_cost(&[34, 56, 78, 100], &[25, 50])
+        );
+    }
+
+    #[test]
+    fn symmetric_placement() {
+        let sol = get_quadrant_cache_locations(symmetric(100, 60), symmetric(3, 1)).unwrap();
+        let quadrant = get_cache_locations(100u64, 60, 3, 1);
+
+        assert_eq!(sol.caches().pos_x, sol.caches().neg_x);
+        assert_eq!(sol.caches().pos_y, sol.caches().neg_y);
+        assert!(sol.cost() <= 4 * quadrant.cost());
+    }
+
+    #[test]
+    fn off_centre_producer() {
+        let extents = HalfAxes {
+            pos_x: 150,
+            neg_x: 30,
+            pos_y: 80,
+            neg_y: 80,
+        };
+        let sol = find_best_quadrant_split(extents, 6).unwrap();
+        let budgets = sol.budgets();
+
+        assert_eq!(
+            budgets.pos_x + budgets.neg_x + budgets.pos_y + budgets.neg_y,
+            6
+        );
+        assert!(budgets.pos_x > budgets.neg_x);
+        assert_eq!(sol.cost(), get_quadrant_cost(&extents, sol.caches()));
+    }
+
+    #[test]
+    fn caches_must_fit() {
+        assert!(get_quadrant_cache_locations(symmetric(3, 3), symmetric(3, 0)).is_none());
+        assert!(find_best_quadrant_split(symmetric(2, 2), 5).is_none());
+    }
+}
@@ -0,0 +1,575 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+/*
+ *
+ * Copyright (c) 2022 Universidade de Vigo
+ *
+ * This program is free software; you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License version 2 as
+ * published by the Free Software Foundation;
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program; if not, write to the Free Software
+ * Foundation, Inc., 59 Temple Place, Suite 330, Boston, MA  02111-1307  USA
+ *
+ * Author: Miguel Rodríguez Pérez <miguel@det.uvigo.gal>
+ *
+ */
+
+//! Local HTTP service, built with the `server` feature.
+//!
+//! All the endpoints take a JSON document in the body of a `POST` request and
+//! answer with another one:
+//!
+//!   * `/solve`: takes a [`ProblemDescription`] and returns its [`Solution`].
+//!   * `/eval`: takes an [`EvalRequest`] and returns an [`EvalResponse`].
+//!   * `/frontier`: takes a [`FrontierRequest`] and returns a list of
+//!     [`FrontierPoint`] with the best placement for every number of caches.
+//!
+//! Requests are read by a fixed number of threads and solved in a rayon pool
+//! shared by all the clients. The last solutions are kept in memory so that
+//! repeated requests are answered at once. As their cost grows very fast,
+//! requests are limited to at most [`MAX_FAILURES`](crate::MAX_FAILURES)
+//! failures, [`MAX_CACHES`] caches, or [`MAX_WORST_CASE_CACHES`] with
+//! failures, grids of [`MAX_GRID_SIZE`] nodes per side and bodies of
+//! [`MAX_BODY_SIZE`] bytes.
+
+use std::{
+    collections::HashMap,
+    error::Error,
+    hash::Hash,
+    io::{self, Read},
+    net::ToSocketAddrs,
+    panic::{catch_unwind, AssertUnwindSafe},
+    sync::Mutex,
+    thread,
+};
+
+use rayon::{ThreadPool, ThreadPoolBuilder};
+use serde::{Deserialize, Serialize};
+use tiny_http::{Header, Method, Request, Response, Server};
+
+use crate::{
+    cancel::Outcome,
+    cost::{get_grid_cost, get_worst_case_cost, is_valid_placement},
+    find_best_split, Objective, ProblemDescription, Solution, SplitOptions,
+};
+
+/// Largest number of caches of a request, or of the largest placement of a
+/// frontier request.
+pub const MAX_CACHES: usize = 64;
+
+/// Largest number of caches of a request with a worst-case objective, whose
+/// cost is evaluated for every combination of failed caches.
+pub const MAX_WORST_CASE_CACHES: usize = 16;
+
+/// Largest width and height of the grid of a request.
+pub const MAX_GRID_SIZE: u64 = 10_000;
+
+/// Largest body of a request, in bytes.
+pub const MAX_BODY_SIZE: usize = 64 * 1024;
+
+/// Threads reading and answering requests. Further connections wait for one
+/// of them.
+const WORKERS: usize = 16;
+
+/// Solutions of every kind kept in memory.
+const MEMO_CAPACITY: usize = 1024;
+
+/// Placement whose cost is to be evaluated.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EvalRequest {
+    pub width: u64,
+    pub height: u64,
+    pub horizontal_caches: Vec<u64>,
+    pub vertical_caches: Vec<u64>,
+    #[serde(default)]
+    pub objective: Objective,
+}
+
+/// Cost of an evaluated placement.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EvalResponse {
+    pub cost: u64,
+}
+
+/// Grid for which to compute the best placement for every number of caches
+/// from one up to `max_caches`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FrontierRequest {
+    pub width: u64,
+    pub height: u64,
+    pub max_caches: usize,
+    #[serde(default)]
+    pub objective: Objective,
+}
+
+/// Best placement for a number of caches.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FrontierPoint {
+    pub ncaches: usize,
+    pub solution: Solution<u64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct SplitKey {
+    width: u64,
+    height: u64,
+    ncaches: usize,
+    objective: Objective,
+}
+
+/// Solutions already computed by the service.
+#[derive(Debug, Default)]
+struct Memo {
+    solutions: Mutex<HashMap<ProblemDescription, Solution<u64>>>,
+    splits: Mutex<HashMap<SplitKey, Option<Solution<u64>>>>,
+}
+
+impl Memo {
+    fn get_or_insert<K, V, F>(map: &Mutex<HashMap<K, V>>, key: K, solve: F) -> V
+    where
+        K: Eq + Hash + Clone,
+        V: Clone,
+        F: FnOnce() -> V,
+    {
+        if let Some(value) = map.lock().expect("Poisoned memo").get(&key) {
+            return value.clone();
+        }
+
+        // Solve without holding the lock, so other requests can go on
+        let value = solve();
+        let mut map = map.lock().expect("Poisoned memo");
+        if map.len() >= MEMO_CAPACITY && !map.contains_key(&key) {
+            // Forget any solution to make room
+            if let Some(old) = map.keys().next().cloned() {
+                map.remove(&old);
+            }
+        }
+        map.entry(key).or_insert(value).clone()
+    }
+}
+
+/// Placement service, independent of the transport.
+#[derive(Debug)]
+pub struct Service {
+    pool: ThreadPool,
+    memo: Memo,
+}
+
+impl Service {
+    /// Creates a service that solves the requests in a pool of `threads`
+    /// threads, or as many as rayon chooses by default if `None`.
+    pub fn create(threads: Option<usize>) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        let pool = ThreadPoolBuilder::new()
+            .num_threads(threads.unwrap_or(0))
+            // Panics are answered by `respond`; do not abort the process
+            .panic_handler(|_| ())
+            .build()?;
+
+        Ok(Self {
+            pool,
+            memo: Memo::default(),
+        })
+    }
+
+    /// Answers a request, returning the HTTP status code and the JSON body.
+    pub fn handle(&self, method: &str, path: &str, body: &str) -> (u16, String) {
+        if method != "POST" {
+            return error(405, "Only POST requests are accepted");
+        }
+
+        let result = match path {
+            "/solve" => self.parse(body).and_then(|problem| self.solve(problem)),
+            "/eval" => self.parse(body).and_then(|request| self.eval(request)),
+            "/frontier" => self.parse(body).and_then(|request| self.frontier(request)),
+            _ => return error(404, "Unknown endpoint"),
+        };
+
+        match result {
+            Ok(json) => (200, json),
+            Err(message) => error(400, &message),
+        }
+    }
+
+    fn parse<'a, T: Deserialize<'a>>(&self, body: &'a str) -> Result<T, String> {
+        serde_json::from_str(body).map_err(|err| err.to_string())
+    }
+
+    fn solve(&self, problem: ProblemDescription) -> Result<String, String> {
+        check_size(
+            problem.width,
+            problem.height,
+            problem.nhoriz.saturating_add(problem.nvert),
+            problem.objective,
+        )?;
+        problem.validate().map_err(|err| err.to_string())?;
+
+        let sol = Memo::get_or_insert(&self.memo.solutions, problem, || {
+            self.pool.install(|| problem.solve())
+        });
+
+        to_json(&sol)
+    }
+
+    fn eval(&self, request: EvalRequest) -> Result<String, String> {
+        check_size(
+            request.width,
+            request.height,
+            request.horizontal_caches.len() + request.vertical_caches.len(),
+            request.objective,
+        )?;
+        if !is_valid_placement(
+            request.width,
+            request.height,
+            &request.horizontal_caches,
+            &request.vertical_caches,
+        ) {
+            return Err("Caches must be strictly increasing and inside the grid".into());
+        }
+        request
+            .objective
+            .validate()
+            .map_err(|err| err.to_string())?;
+
+        let (width, height) = (request.width, request.height);
+        let (horiz, vert) = (&request.horizontal_caches, &request.vertical_caches);
+        let cost = self.pool.install(|| match request.objective {
+            Objective::Nominal => get_grid_cost(width, height, horiz, vert),
+            Objective::WorstCase { failures } => {
+                get_worst_case_cost(width, height, horiz, vert, failures)
+            }
+        });
+
+        to_json(&EvalResponse { cost })
+    }
+
+    fn frontier(&self, request: FrontierRequest) -> Result<String, String> {
+        check_size(
+            request.width,
+            request.height,
+            request.max_caches,
+            request.objective,
+        )?;
+        request
+            .objective
+            .validate()
+            .map_err(|err| err.to_string())?;
+
+        let points: Vec<_> = (1..=request.max_caches)
+            .filter_map(|ncaches| {
+                let key = SplitKey {
+                    width: request.width,
+                    height: request.height,
+                    ncaches,
+                    objective: request.objective,
+                };
+                let sol = Memo::get_or_insert(&self.memo.splits, key, || {
+                    let options = SplitOptions::default();
+                    self.pool
+                        .install(|| {
+                            find_best_split(key.width, key.height, ncaches, key.objective, &options)
+                        })
+                        .expect("Solving without a store never fails")
+                        .map(Outcome::into_solution)
+                });
+
+                sol.map(|solution| FrontierPoint { ncaches, solution })
+            })
+            .collect();
+
+        to_json(&points)
+    }
+
+    fn respond(&self, mut request: Request) -> io::Result<()> {
+        let (status, json) = match read_body(&mut request) {
+            Ok(body) => {
+                let method = match request.method() {
+                    Method::Post => "POST",
+                    _ => "OTHER",
+                };
+                catch_unwind(AssertUnwindSafe(|| {
+                    self.handle(method, request.url(), &body)
+                }))
+                .unwrap_or_else(|_| error(500, "Internal error"))
+            }
+            Err(answer) => answer,
+        };
+
+        let content_type =
+            Header::from_bytes("Content-Type", "application/json").expect("Valid header");
+        request.respond(
+            Response::from_string(json)
+                .with_status_code(status)
+                .with_header(content_type),
+        )
+    }
+}
+
+/// Checks the size of a request for a `width`×`height` grid with `ncaches`
+/// caches, before doing any work.
+fn check_size(width: u64, height: u64, ncaches: usize, objective: Objective) -> Result<(), String> {
+    let max_caches = match objective {
+        Objective::Nominal => MAX_CACHES,
+        Objective::WorstCase { .. } => MAX_WORST_CASE_CACHES,
+    };
+
+    if width > MAX_GRID_SIZE || height > MAX_GRID_SIZE {
+        Err(format!("Grids have at most {MAX_GRID_SIZE} nodes per side"))
+    } else if ncaches > max_caches {
+        Err(format!("Requests have at most {max_caches} caches"))
+    } else {
+        Ok(())
+    }
+}
+
+/// Reads the body of `request`, or returns the answer to send instead if it is
+/// too large or not valid UTF-8.
+fn read_body(request: &mut Request) -> Result<String, (u16, String)> {
+    let too_large = || error(413, &format!("Bodies have at most {MAX_BODY_SIZE} bytes"));
+    if request.body_length().is_some_and(|len| len > MAX_BODY_SIZE) {
+        return Err(too_large());
+    }
+
+    let mut body = String::new();
+    request
+        .as_reader()
+        .take(MAX_BODY_SIZE as u64 + 1)
+        .read_to_string(&mut body)
+        .map_err(|_| error(400, "Body is not valid UTF-8"))?;
+    if body.len() > MAX_BODY_SIZE {
+        return Err(too_large());
+    }
+
+    Ok(body)
+}
+
+fn to_json<T: Serialize>(value: &T) -> Result<String, String> {
+    serde_json::to_string(value).map_err(|err| err.to_string())
+}
+
+fn error(status: u16, message: &str) -> (u16, String) {
+    (status, serde_json::json!({ "error": message }).to_string())
+}
+
+/// Answers the requests received by `server` until it is closed.
+///
+/// Requests are read and answered by a fixed number of threads, so slow
+/// clients do not hold the threads of the pool that solves the requests, and
+/// many clients do not start unbounded threads.
+pub fn run(server: Server, service: Service) {
+    let (server, service) = (&server, &service);
+
+    thread::scope(|scope| {
+        for _ in 0..WORKERS {
+            scope.spawn(move || {
+                for request in server.incoming_requests() {
+                    // The client may have gone away; there is nobody to tell
+                    let _ = service.respond(request);
+                }
+            });
+        }
+    });
+}
+
+/// Listens for requests in `addr` and answers them until the process ends.
+pub fn serve<A: ToSocketAddrs>(
+    addr: A,
+    threads: Option<usize>,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let server = Server::http(addr)?;
+    run(server, Service::create(threads)?);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashMap,
+        io::{Read, Write},
+        net::{SocketAddr, TcpStream},
+        sync::Mutex,
+        thread,
+    };
+
+    use tiny_http::Server;
+
+    use crate::Solution;
+
+    use super::{run, FrontierPoint, Memo, Service, MAX_BODY_SIZE, MEMO_CAPACITY};
+
+    #[test]
+    fn solve_and_memo() {
+        let service = Service::create(Some(2)).unwrap();
+        let body = r#"{"width": 100, "height": 80, "nhoriz": 2, "nvert": 2}"#;
+
+        let (status, json) = service.handle("POST", "/solve", body);
+        assert_eq!(status, 200);
+        let sol: Solution<u64> = serde_json::from_str(&json).unwrap();
+        assert_eq!(sol.cost(), 350_224);
+
+        assert_eq!(service.handle("POST", "/solve", body), (status, json));
+        assert_eq!(service.memo.solutions.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn eval() {
+        let service = Service::create(Some(1)).unwrap();
+        let body = r#"{"width": 100, "height": 40, "horizontal_caches": [32, 54, 77],
+            "vertical_caches": [20]}"#;
+
+        assert_eq!(
+            service.handle("POST", "/eval", body),
+            (200, r#"{"cost":114617}"#.to_owned())
+        );
+    }
+
+    #[test]
+    fn frontier() {
+        let service = Service::create(Some(1)).unwrap();
+        let body = r#"{"width": 100, "height": 80, "max_caches": 4}"#;
+
+        let (status, json) = service.handle("POST", "/frontier", body);
+        assert_eq!(status, 200);
+        let points: Vec<FrontierPoint> = serde_json::from_str(&json).unwrap();
+        assert_eq!(points.len(), 4);
+        assert!(points
+            .windows(2)
+            .all(|pair| pair[1].solution.cost() <= pair[0].solution.cost()));
+        assert_eq!(points[3].solution.cost(), 350_224);
+    }
+
+    #[test]
+    fn errors() {
+        let service = Service::create(Some(1)).unwrap();
+
+        assert_eq!(service.handle("GET", "/solve", "").0, 405);
+        assert_eq!(service.handle("POST", "/unknown", "{}").0, 404);
+        assert_eq!(service.handle("POST", "/solve", "{").0, 400);
+        assert_eq!(
+            service
+                .handle(
+                    "POST",
+                    "/solve",
+                    r#"{"width": 100, "height": 80, "nhoriz": 1, "nvert": 80}"#
+                )
+                .0,
+            400
+        );
+    }
+
+    #[test]
+    fn limits() {
+        let service = Service::create(Some(1)).unwrap();
+        let rejected = |path: &str, body: &str| service.handle("POST", path, body).0 == 400;
+
+        assert!(rejected(
+            "/solve",
+            r#"{"width": 10, "height": 10, "nhoriz": 0, "nvert": 0}"#
+        ));
+        assert!(rejected(
+            "/solve",
+            r#"{"width": 100, "height": 80, "nhoriz": 2, "nvert": 2,
+                "objective": {"WorstCase": {"failures": 5}}}"#
+        ));
+        assert!(rejected(
+            "/eval",
+            r#"{"width": 100, "height": 40, "horizontal_caches": [32, 54, 77],
+                "vertical_caches": [20], "objective": {"WorstCase": {"failures": 5}}}"#
+        ));
+        assert!(rejected(
+            "/frontier",
+            r#"{"width": 1000, "height": 1000, "max_caches": 1000}"#
+        ));
+        assert!(rejected(
+            "/frontier",
+            r#"{"width": 1000, "height": 1000, "max_caches": 17,
+                "objective": {"WorstCase": {"failures": 1}}}"#
+        ));
+        assert!(rejected(
+            "/solve",
+            r#"{"width": 1000, "height": 1000, "nhoriz": 40, "nvert": 40}"#
+        ));
+        assert!(rejected(
+            "/solve",
+            r#"{"width": 100000, "height": 80, "nhoriz": 2, "nvert": 2}"#
+        ));
+
+        let caches = |n: u64| (1..=n).map(|x| x.to_string()).collect::<Vec<_>>().join(",");
+        assert!(rejected(
+            "/eval",
+            &format!(
+                r#"{{"width": 1000, "height": 1000, "horizontal_caches": [{}],
+                    "vertical_caches": [{}]}}"#,
+                caches(40),
+                caches(40)
+            )
+        ));
+        assert!(rejected(
+            "/eval",
+            &format!(
+                r#"{{"width": 1000, "height": 1000, "horizontal_caches": [{}],
+                    "vertical_caches": [], "objective": {{"WorstCase": {{"failures": 4}}}}}}"#,
+                caches(17)
+            )
+        ));
+    }
+
+    #[test]
+    fn bounded_memo() {
+        let memo = Mutex::new(HashMap::new());
+        for key in 0..MEMO_CAPACITY + 10 {
+            assert_eq!(Memo::get_or_insert(&memo, key, || 2 * key), 2 * key);
+        }
+
+        assert_eq!(memo.lock().unwrap().len(), MEMO_CAPACITY);
+    }
+
+    fn post(addr: SocketAddr, path: &str, body: &str) -> String {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        write!(
+            stream,
+            "POST {path} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\
+             Content-Type: application/json\r\nContent-Length: {}\r\n\r\n{body}",
+            body.len()
+        )
+        .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+
+        response
+    }
+
+    fn start() -> SocketAddr {
+        let server = Server::http("127.0.0.1:0").unwrap();
+        let addr = server.server_addr().to_ip().unwrap();
+        thread::spawn(move || run(server, Service::create(Some(1)).unwrap()));
+
+        addr
+    }
+
+    #[test]
+    fn over_http() {
+        let body = r#"{"width": 100, "height": 50, "nhoriz": 3, "nvert": 1}"#;
+        let response = post(start(), "/solve", body);
+
+        assert!(response.starts_with("HTTP/1.1 200"));
+        assert!(response
+            .ends_with(r#"{"cost":164053,"vertical_caches":[25],"horizontal_caches":[34,56,78]}"#));
+    }
+
+    #[test]
+    fn large_body() {
+        let body = format!(
+            r#"{{"width": 100, "height": 50, "nhoriz": 3, "nvert": 1, "padding": "{}"}}"#,
+            " ".repeat(MAX_BODY_SIZE)
+        );
+
+        assert!(post(start(), "/solve", &body).starts_with("HTTP/1.1 413"));
+    }
+}
@@ -0,0 +1,425 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+/*
+ *
+ * Copyright (c) 2022 Universidade de Vigo
+ *
+ * This program is free software; you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License version 2 as
+ * published by the Free Software Foundation;
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program; if not, write to the Free Software
+ * Foundation, Inc., 59 Temple Place, Suite 330, Boston, MA  02111-1307  USA
+ *
+ * Author: Miguel Rodríguez Pérez <miguel@det.uvigo.gal>
+ *
+ */
+
+use std::{
+    cmp::min,
+    io,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+
+use crate::{
+    cancel::{CancelToken, Outcome, Watch},
+    demand::Demand,
+    get_weighted_cache_locations,
+    gridsearcher::{anneal_all, search_both, Layout, Searcher},
+    progress::{Progress, ProgressTracker},
+    store::SolutionStore,
+    trace::{trace, TraceEvent},
+    AnnealParams, Objective, ProblemDescription, Solution,
+};
+
+/// Algorithm that places the caches of every split.
+#[derive(Debug, Clone, Copy)]
+pub enum SplitSolver<'a> {
+    /// Local search. The solutions of the splits are looked for in `store`
+    /// first, and the ones not found there are stored once searched to the
+    /// end.
+    Local { store: Option<&'a SolutionStore> },
+    /// Simulated annealing. Its solutions are never stored.
+    Anneal(&'a AnnealParams),
+}
+
+impl Default for SplitSolver<'_> {
+    fn default() -> Self {
+        Self::Local { store: None }
+    }
+}
+
+/// Options of [`find_best_split`]. By default, the splits are placed with the
+/// local search, without a store, and nothing stops or follows the search.
+#[derive(Clone, Copy, Default)]
+pub struct SplitOptions<'a> {
+    /// Algorithm that places the caches of every split.
+    pub solver: SplitSolver<'a>,
+    /// Stops the searches when cancelled.
+    pub token: Option<&'a CancelToken>,
+    /// Called every time the search of a split finishes.
+    pub progress: Option<&'a (dyn Fn(&Progress) + Sync)>,
+    /// Called with every event of the searches of all the splits, which run in
+    /// parallel. The splits found in the store are not searched, so they have
+    /// no events.
+    pub trace: Option<&'a (dyn Fn(TraceEvent) + Sync)>,
+}
+
+/// Looks for the best way of splitting `ncaches` between the horizontal and
+/// the vertical axes, and for the placement of the caches in that split.
+///
+/// Returns `None` if there are no caches or no valid split for the grid. The
+/// outcome is not final if the token of `options` stopped any search, and
+/// errors only come from the store.
+pub fn find_best_split(
+    width: u64,
+    height: u64,
+    ncaches: usize,
+    objective: Objective,
+    options: &SplitOptions,
+) -> io::Result<Option<Outcome<Solution<u64>>>> {
+    let never = CancelToken::new();
+    let token = options.token.unwrap_or(&never);
+    let tracker = options
+        .progress
+        .map(|callback| ProgressTracker::create(splits(width, height, ncaches).count(), callback));
+    let interrupted = AtomicBool::new(false);
+
+    let best = search_splits(width, height, ncaches, objective, |problem| {
+        let store = match options.solver {
+            SplitSolver::Local { store } => store,
+            SplitSolver::Anneal(_) => None,
+        };
+
+        let sol = match store.and_then(|store| store.get(&problem)) {
+            Some(sol) => sol,
+            None => {
+                let watch = Watch::create(token);
+                let sol = solve_split(&problem, options, &watch);
+                if watch.interrupted() {
+                    interrupted.store(true, Ordering::Relaxed);
+                } else if let Some(store) = store {
+                    store.insert(problem, sol.clone())?;
+                }
+
+                sol
+            }
+        };
+        if let Some(tracker) = &tracker {
+            tracker.record(sol.cost());
+        }
+
+        Ok(sol)
+    })?;
+
+    let complete = !interrupted.load(Ordering::Relaxed);
+    Ok(best.map(|sol| Outcome::create(sol, complete)))
+}
+
+/// Places the caches of `problem` with the solver of `options`, stopping when
+/// `watch` says so.
+fn solve_split(
+    problem: &ProblemDescription,
+    options: &SplitOptions,
+    watch: &Watch,
+) -> Solution<u64> {
+    let &ProblemDescription {
+        width,
+        height,
+        nhoriz,
+        nvert,
+        objective,
+    } = problem;
+    let search = |searcher: &mut dyn Searcher<u64>, layout: Layout| match options.trace {
+        Some(callback) => trace(searcher, layout, nhoriz, nvert, callback, &mut &*watch),
+        None => searcher.find_solution_observed(&mut &*watch),
+    };
+
+    match options.solver {
+        SplitSolver::Local { .. } => search_both(width, height, nhoriz, nvert, objective, search),
+        SplitSolver::Anneal(params) => {
+            anneal_all(width, height, nhoriz, nvert, objective, params, search)
+        }
+    }
+}
+
+/// Like [`find_best_split`], but minimising the hops weighted by `demand`, in
+/// a grid with the size of `demand`.
+///
+/// A skewed demand may favour the shorter axis, so every split is searched,
+/// not just those with more caches in the longer axis.
+pub fn find_best_weighted_split(demand: &Demand, ncaches: usize) -> Option<Solution<u64>> {
+    let solutions: Vec<_> = all_splits(demand.width(), demand.height(), ncaches)
+        .map(|(nhoriz, nvert)| get_weighted_cache_locations(demand, nhoriz, nvert))
+        .collect();
+
+    solutions.into_iter().reduce(min)
+}
+
+fn search_splits<F>(
+    width: u64,
+    height: u64,
+    ncaches: usize,
+    objective: Objective,
+    solve: F,
+) -> io::Result<Option<Solution<u64>>>
+where
+    F: Fn(ProblemDescription) -> io::Result<Solution<u64>> + Sync,
+{
+    let solutions: io::Result<Vec<_>> = splits(width, height, ncaches)
+        .map(|(nhoriz, nvert)| {
+            solve(ProblemDescription {
+                width,
+                height,
+                nhoriz,
+                nvert,
+                objective,
+            })
+        })
+        .collect();
+
+    // Keep the first split among those with the same cost
+    Ok(solutions?.into_iter().reduce(min))
+}
+
+/// Horizontal and vertical caches of the splits that fit in the grid, with at
+/// least as many caches in the longer axis as in the shorter one. There are no
+/// splits without caches.
+fn splits(width: u64, height: u64, ncaches: usize) -> impl ParallelIterator<Item = (usize, usize)> {
+    splits_up_to(width, height, ncaches, ncaches / 2)
+}
+
+/// Like [`splits`], but with any number of caches in the shorter axis.
+fn all_splits(
+    width: u64,
+    height: u64,
+    ncaches: usize,
+) -> impl ParallelIterator<Item = (usize, usize)> {
+    splits_up_to(width, height, ncaches, ncaches)
+}
+
+/// Splits that fit in the grid with at most `shorter` caches in the shorter
+/// axis, starting with the fewest.
+fn splits_up_to(
+    width: u64,
+    height: u64,
+    ncaches: usize,
+    shorter: usize,
+) -> impl ParallelIterator<Item = (usize, usize)> {
+    (0..=shorter)
+        .into_par_iter()
+        .map(move |fewer| {
+            if width >= height {
+                (ncaches - fewer, fewer)
+            } else {
+                (fewer, ncaches - fewer)
+            }
+        })
+        .filter(move |&(nhoriz, nvert)| {
+            nhoriz + nvert > 0 && width > nhoriz as u64 && height > nvert as u64
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, process, sync::Mutex};
+
+    use crate::{
+        cancel::CancelToken,
+        demand::{Demand, DemandModel},
+        get_annealed_cache_locations, get_cache_locations, get_weighted_cache_locations,
+        store::SolutionStore,
+        AnnealParams, Objective, ProblemDescription, Solution,
+    };
+
+    use super::{find_best_split, find_best_weighted_split, SplitOptions, SplitSolver};
+
+    fn best_split(
+        width: u64,
+        height: u64,
+        ncaches: usize,
+        objective: Objective,
+    ) -> Option<Solution<u64>> {
+        find_best_split(width, height, ncaches, objective, &SplitOptions::default())
+            .unwrap()
+            .map(|outcome| {
+                assert!(outcome.is_final());
+                outcome.into_solution()
+            })
+    }
+
+    #[test]
+    fn best_split_100_80_4() {
+        let sol = best_split(100, 80, 4, Objective::Nominal).unwrap();
+
+        assert_eq!(sol, get_cache_locations(100, 80, 2, 2));
+    }
+
+    #[test]
+    fn no_caches() {
+        assert_eq!(best_split(100, 80, 0, Objective::Nominal), None);
+        assert_eq!(
+            best_split(100, 80, 0, Objective::WorstCase { failures: 1 }),
+            None
+        );
+    }
+
+    #[test]
+    fn resilient_split_is_never_cheaper() {
+        let nominal = best_split(100, 60, 4, Objective::Nominal).unwrap();
+        let resilient = best_split(100, 60, 4, Objective::WorstCase { failures: 1 }).unwrap();
+
+        assert!(resilient.cost() > nominal.cost());
+    }
+
+    #[test]
+    fn taller_than_wide() {
+        let wide = best_split(100, 60, 4, Objective::Nominal).unwrap();
+        let tall = best_split(60, 100, 4, Objective::Nominal).unwrap();
+
+        assert_eq!(tall, wide.transposed());
+    }
+
+    #[test]
+    fn progress_reports_every_split() {
+        let reports = Mutex::new(Vec::new());
+        let progress = |progress: &_| reports.lock().unwrap().push(*progress);
+        let options = SplitOptions {
+            progress: Some(&progress),
+            ..Default::default()
+        };
+        let sol = find_best_split(100, 80, 4, Objective::Nominal, &options)
+            .unwrap()
+            .unwrap()
+            .into_solution();
+        let reports = reports.into_inner().unwrap();
+
+        assert_eq!(reports.len(), 3);
+        assert!(reports.iter().all(|progress| progress.total == 3));
+        assert_eq!(reports[2].best_cost, Some(sol.cost()));
+    }
+
+    #[test]
+    fn options_combine() {
+        let dir = env::temp_dir().join(format!("fastgridcache-split-{}", process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let store = SolutionStore::open(&dir).unwrap();
+        let token = CancelToken::new();
+        let reports = Mutex::new(0);
+        let progress = |_: &_| *reports.lock().unwrap() += 1;
+        let options = SplitOptions {
+            solver: SplitSolver::Local {
+                store: Some(&store),
+            },
+            token: Some(&token),
+            progress: Some(&progress),
+            trace: None,
+        };
+
+        let outcome = find_best_split(100, 80, 4, Objective::Nominal, &options)
+            .unwrap()
+            .unwrap();
+        assert!(outcome.is_final());
+        assert_eq!(*outcome.solution(), get_cache_locations(100, 80, 2, 2));
+        assert_eq!(*reports.lock().unwrap(), 3);
+        let problem = ProblemDescription {
+            width: 100,
+            height: 80,
+            nhoriz: 2,
+            nvert: 2,
+            objective: Objective::Nominal,
+        };
+        assert_eq!(store.get(&problem), Some(outcome.into_solution()));
+
+        // Stopped searches are not stored
+        let other = CancelToken::new();
+        other.cancel();
+        let options = SplitOptions {
+            token: Some(&other),
+            ..options
+        };
+        let outcome = find_best_split(400, 300, 6, Objective::Nominal, &options)
+            .unwrap()
+            .unwrap();
+        assert!(!outcome.is_final());
+        assert_eq!(
+            store.get(&ProblemDescription {
+                width: 400,
+                height: 300,
+                nhoriz: 3,
+                nvert: 3,
+                ..problem
+            }),
+            None
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn annealed_split_with_trace() {
+        let params = AnnealParams {
+            restarts: 1,
+            ..Default::default()
+        };
+        let events = Mutex::new(0);
+        let trace = |_| *events.lock().unwrap() += 1;
+        let options = SplitOptions {
+            solver: SplitSolver::Anneal(&params),
+            trace: Some(&trace),
+            ..Default::default()
+        };
+        let sol = find_best_split(100, 80, 2, Objective::Nominal, &options)
+            .unwrap()
+            .unwrap()
+            .into_solution();
+
+        let by_split = [(2, 0), (1, 1)]
+            .map(|(nhoriz, nvert)| {
+                get_annealed_cache_locations(100, 80, nhoriz, nvert, Objective::Nominal, &params)
+            })
+            .into_iter()
+            .min()
+            .unwrap();
+        assert_eq!(sol, by_split);
+        assert!(*events.lock().unwrap() > 0);
+    }
+
+    #[test]
+    fn uniform_weighted_split() {
+        let sol = find_best_weighted_split(&Demand::uniform(100, 80), 4).unwrap();
+
+        assert_eq!(
+            sol.cost(),
+            best_split(100, 80, 4, Objective::Nominal).unwrap().cost()
+        );
+    }
+
+    #[test]
+    fn weighted_split_on_the_shorter_axis() {
+        let demand = DemandModel::Hotspot {
+            x: 3.0,
+            y: 50.0,
+            sigma: 4.0,
+        }
+        .generate(100, 60);
+        let sol = find_best_weighted_split(&demand, 4).unwrap();
+
+        assert!(sol.vertical_caches().len() > sol.horizontal_caches().len());
+        assert_eq!(
+            sol.cost(),
+            demand.cost(sol.horizontal_caches(), sol.vertical_caches())
+        );
+        for nvert in 0..=2 {
+            let longer = get_weighted_cache_locations(&demand, 4 - nvert, nvert);
+            assert!(sol.cost() < longer.cost());
+        }
+    }
+}
@@ -0,0 +1,370 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+/*
+ *
+ * Copyright (c) 2022 Universidade de Vigo
+ *
+ * This program is free software; you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License version 2 as
+ * published by the Free Software Foundation;
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program; if not, write to the Free Software
+ * Foundation, Inc., 59 Temple Place, Suite 330, Boston, MA  02111-1307  USA
+ *
+ * Author: Miguel Rodríguez Pérez <miguel@det.uvigo.gal>
+ *
+ */
+
+//! Persistent store of solutions.
+//!
+//! Solutions are appended to a text file in a cache directory, one per line,
+//! keyed by the problem parameters, the objective and the version of the crate,
+//! so that results from older versions are never reused.
+
+use std::{
+    collections::HashMap,
+    env,
+    fs::{self, File, OpenOptions},
+    io::{self, BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use crate::{
+    cost::{get_grid_cost, get_worst_case_cost, is_valid_placement},
+    Objective, ProblemDescription, Solution,
+};
+
+const STORE_FILE: &str = "solutions.log";
+const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Append-only store of solutions kept in a directory.
+#[derive(Debug)]
+pub struct SolutionStore {
+    path: PathBuf,
+    state: Mutex<State>,
+}
+
+#[derive(Debug)]
+struct State {
+    solutions: HashMap<ProblemDescription, Solution<u64>>,
+    file: File,
+}
+
+impl SolutionStore {
+    /// Opens the store in `dir`, creating it if needed.
+    ///
+    /// Lines that cannot be parsed, whose solution does not match its problem,
+    /// or that were written by other versions of the crate are ignored, as is
+    /// a last line cut short while it was appended.
+    pub fn open<P: AsRef<Path>>(dir: P) -> io::Result<Self> {
+        fs::create_dir_all(&dir)?;
+        let path = dir.as_ref().join(STORE_FILE);
+        let file = OpenOptions::new()
+            .create(true)
+            .read(true)
+            .append(true)
+            .open(&path)?;
+
+        let mut solutions = HashMap::new();
+        let mut reader = BufReader::new(&file);
+        let mut line = String::new();
+        let mut complete = true;
+        while reader.read_line(&mut line)? > 0 {
+            complete = line.ends_with('\n');
+            if let Some((problem, sol)) = line.strip_suffix('\n').and_then(parse_record) {
+                solutions.insert(problem, sol);
+            }
+            line.clear();
+        }
+
+        let mut file = file;
+        if !complete {
+            // Keep the next record out of the line that was cut short
+            file.write_all(b"\n")?;
+        }
+
+        Ok(Self {
+            path,
+            state: Mutex::new(State { solutions, file }),
+        })
+    }
+
+    /// Opens the store in the default cache directory, that is
+    /// `$XDG_CACHE_HOME/fastgridcache` or `$HOME/.cache/fastgridcache`.
+    pub fn open_default() -> io::Result<Self> {
+        let dir = default_dir().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::NotFound, "Cannot find a cache directory")
+        })?;
+
+        Self::open(dir)
+    }
+
+    /// Get the path of the file holding the solutions.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Get the number of stored solutions.
+    pub fn len(&self) -> usize {
+        self.state().solutions.len()
+    }
+
+    /// Whether no solution is stored.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Get the stored solution of `problem`, if any.
+    pub fn get(&self, problem: &ProblemDescription) -> Option<Solution<u64>> {
+        self.state().solutions.get(problem).cloned()
+    }
+
+    /// Stores the solution of `problem`.
+    pub fn insert(&self, problem: ProblemDescription, sol: Solution<u64>) -> io::Result<()> {
+        let mut state = self.state();
+
+        // A single write to a file opened for appending keeps the records of
+        // concurrent processes from interleaving
+        let record = format_record(&problem, &sol) + "\n";
+        state.file.write_all(record.as_bytes())?;
+        state.solutions.insert(problem, sol);
+
+        Ok(())
+    }
+
+    /// Get the solution of `problem` from the store, or solve it and store
+    /// the solution.
+    pub fn solve(&self, problem: ProblemDescription) -> io::Result<Solution<u64>> {
+        if let Some(sol) = self.get(&problem) {
+            return Ok(sol);
+        }
+
+        let sol = problem.solve();
+        self.insert(problem, sol.clone())?;
+
+        Ok(sol)
+    }
+
+    /// Removes every stored solution.
+    pub fn clear(&self) -> io::Result<()> {
+        let mut state = self.state();
+
+        state.file.set_len(0)?;
+        state.solutions.clear();
+
+        Ok(())
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().expect("Poisoned solution store")
+    }
+}
+
+/// Default directory of the store, if there is one.
+pub fn default_dir() -> Option<PathBuf> {
+    env::var_os("XDG_CACHE_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".cache")))
+        .map(|dir| dir.join("fastgridcache"))
+}
+
+fn format_objective(objective: Objective) -> String {
+    match objective {
+        Objective::Nominal => "nominal".to_owned(),
+        Objective::WorstCase { failures } => format!("worst-case:{failures}"),
+    }
+}
+
+fn parse_objective(text: &str) -> Option<Objective> {
+    match text.split_once(':') {
+        None if text == "nominal" => Some(Objective::Nominal),
+        Some(("worst-case", failures)) => Some(Objective::WorstCase {
+            failures: failures.parse().ok()?,
+        }),
+        _ => None,
+    }
+}
+
+fn format_list(values: &[u64]) -> String {
+    values
+        .iter()
+        .map(u64::to_string)
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+fn parse_list(text: &str) -> Option<Box<[u64]>> {
+    if text.is_empty() {
+        return Some(Box::new([]));
+    }
+
+    text.split(',').map(|value| value.parse().ok()).collect()
+}
+
+fn format_record(problem: &ProblemDescription, sol: &Solution<u64>) -> String {
+    format!(
+        "{VERSION}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+        problem.width,
+        problem.height,
+        problem.nhoriz,
+        problem.nvert,
+        format_objective(problem.objective),
+        sol.cost(),
+        format_list(sol.horizontal_caches()),
+        format_list(sol.vertical_caches())
+    )
+}
+
+fn parse_record(line: &str) -> Option<(ProblemDescription, Solution<u64>)> {
+    let fields: Vec<_> = line.split('\t').collect();
+    let [version, width, height, nhoriz, nvert, objective, cost, horiz, vert] = fields[..] else {
+        return None;
+    };
+    if version != VERSION {
+        return None;
+    }
+
+    let problem = ProblemDescription {
+        width: width.parse().ok()?,
+        height: height.parse().ok()?,
+        nhoriz: nhoriz.parse().ok()?,
+        nvert: nvert.parse().ok()?,
+        objective: parse_objective(objective)?,
+    };
+    let (horiz, vert) = (parse_list(horiz)?, parse_list(vert)?);
+    if problem.validate().is_err()
+        || horiz.len() != problem.nhoriz
+        || vert.len() != problem.nvert
+        || !is_valid_placement(problem.width, problem.height, &horiz, &vert)
+    {
+        return None;
+    }
+
+    // Check the cost too, as a damaged record may still look valid
+    let cost = cost.parse().ok()?;
+    let actual = match problem.objective {
+        Objective::Nominal => get_grid_cost(problem.width, problem.height, &horiz, &vert),
+        Objective::WorstCase { failures } => {
+            get_worst_case_cost(problem.width, problem.height, &horiz, &vert, failures)
+        }
+    };
+    if cost != actual {
+        return None;
+    }
+
+    Some((problem, Solution::create(cost, horiz, vert)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, path::PathBuf, process};
+
+    use crate::{Objective, ProblemDescription};
+
+    use super::{format_record, SolutionStore, VERSION};
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("fastgridcache-{name}-{}", process::id()));
+        let _ = fs::remove_dir_all(&dir);
+
+        dir
+    }
+
+    fn problem(objective: Objective) -> ProblemDescription {
+        ProblemDescription {
+            width: 100,
+            height: 60,
+            nhoriz: 3,
+            nvert: 1,
+            objective,
+        }
+    }
+
+    #[test]
+    fn solutions_persist() {
+        let dir = temp_dir("persist");
+        let nominal = problem(Objective::Nominal);
+        let resilient = problem(Objective::WorstCase { failures: 1 });
+
+        let store = SolutionStore::open(&dir).unwrap();
+        let sol = store.solve(nominal).unwrap();
+        store.solve(resilient).unwrap();
+        drop(store);
+
+        let store = SolutionStore::open(&dir).unwrap();
+        assert_eq!(store.len(), 2);
+        assert_eq!(store.get(&nominal), Some(sol));
+        assert_eq!(store.get(&resilient), Some(resilient.solve()));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn invalid_records_are_ignored() {
+        let dir = temp_dir("invalid");
+        fs::create_dir_all(&dir).unwrap();
+        let nominal = problem(Objective::Nominal);
+        let record = format_record(&nominal, &nominal.solve());
+        fs::write(
+            dir.join("solutions.log"),
+            format!(
+                "0.0.0\t100\t60\t3\t1\tnominal\t220407\t27,54,77\t37\n\
+                 garbage\n\
+                 {VERSION}\t100\t60\t3\t1\tnominal\t1\t27,54,77\t37\n\
+                 {VERSION}\t100\t60\t3\t1\tnominal\t220407\t27,54\t37\n\
+                 {record}\n"
+            ),
+        )
+        .unwrap();
+
+        let store = SolutionStore::open(&dir).unwrap();
+        assert_eq!(store.len(), 1);
+        assert_eq!(store.get(&nominal), Some(nominal.solve()));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn truncated_record() {
+        let dir = temp_dir("truncated");
+        fs::create_dir_all(&dir).unwrap();
+        let nominal = problem(Objective::Nominal);
+        let record = format_record(&nominal, &nominal.solve());
+        assert!(record.ends_with("\t37"));
+        fs::write(dir.join("solutions.log"), &record[..record.len() - 1]).unwrap();
+
+        let store = SolutionStore::open(&dir).unwrap();
+        assert!(store.is_empty());
+        let resilient = problem(Objective::WorstCase { failures: 1 });
+        store.solve(resilient).unwrap();
+        drop(store);
+
+        let store = SolutionStore::open(&dir).unwrap();
+        assert_eq!(store.len(), 1);
+        assert_eq!(store.get(&resilient), Some(resilient.solve()));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn clear() {
+        let dir = temp_dir("clear");
+
+        let store = SolutionStore::open(&dir).unwrap();
+        store.solve(problem(Objective::Nominal)).unwrap();
+        store.clear().unwrap();
+        assert!(store.is_empty());
+        drop(store);
+
+        assert!(SolutionStore::open(&dir).unwrap().is_empty());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
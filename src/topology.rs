@@ -0,0 +1,474 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+/*
+ *
+ * Copyright (c) 2022 Universidade de Vigo
+ *
+ * This program is free software; you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License version 2 as
+ * published by the Free Software Foundation;
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program; if not, write to the Free Software
+ * Foundation, Inc., 59 Temple Place, Suite 330, Boston, MA  02111-1307  USA
+ *
+ * Author: Miguel Rodríguez Pérez <miguel@det.uvigo.gal>
+ *
+ */
+
+//! Cache placement in arbitrary network topologies.
+//!
+//! Interests travel from every node towards the producer along the shortest
+//! path tree rooted at it, and they are served by the first cache that they
+//! find. The caches are placed in that tree so that the total number of hops
+//! is minimal.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    error::Error,
+    fmt::Display,
+    fs, io,
+    path::Path,
+};
+
+/// Errors found while loading a topology.
+#[derive(Debug)]
+pub enum TopologyError {
+    /// The topology could not be read.
+    Io(io::Error),
+    /// A line does not describe an edge.
+    Parse { line: usize },
+    /// The producer is not a node of the topology.
+    UnknownProducer(String),
+}
+
+impl Display for TopologyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TopologyError::Io(err) => write!(f, "Cannot read topology: {err}"),
+            TopologyError::Parse { line } => write!(f, "Invalid edge in line {line}"),
+            TopologyError::UnknownProducer(id) => write!(f, "Unknown producer node {id}"),
+        }
+    }
+}
+
+impl Error for TopologyError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            TopologyError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for TopologyError {
+    fn from(err: io::Error) -> Self {
+        TopologyError::Io(err)
+    }
+}
+
+/// Undirected network topology.
+#[derive(Debug, Default)]
+pub struct Topology {
+    ids: Vec<String>,
+    index: HashMap<String, usize>,
+    neighbors: Vec<Vec<usize>>,
+}
+
+impl Topology {
+    /// Parses a topology from text.
+    ///
+    /// Every line holds an edge as a pair of node IDs separated by white
+    /// space, optionally followed by other fields that are ignored. Empty lines
+    /// and lines starting with `#` are skipped. GraphML-like `<edge
+    /// source="a" target="b"/>` elements are also accepted, and any other
+    /// markup line is ignored.
+    pub fn parse(text: &str) -> Result<Self, TopologyError> {
+        let mut topology = Topology::default();
+
+        for (nline, line) in text.lines().enumerate() {
+            let line = line.trim();
+            let edge = if line.starts_with("<edge") {
+                attribute(line, "source").zip(attribute(line, "target"))
+            } else if line.is_empty() || line.starts_with('#') || line.starts_with('<') {
+                continue;
+            } else {
+                let mut fields = line.split_whitespace();
+                fields.next().zip(fields.next())
+            };
+
+            let (a, b) = edge.ok_or(TopologyError::Parse { line: nline + 1 })?;
+            topology.add_edge(a, b);
+        }
+
+        Ok(topology)
+    }
+
+    /// Loads a topology from a file with the format described in
+    /// [`Topology::parse`].
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, TopologyError> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    /// Get the number of nodes.
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    /// Whether the topology has no nodes.
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+
+    fn add_edge(&mut self, a: &str, b: &str) {
+        let a = self.node(a);
+        let b = self.node(b);
+
+        if a != b && !self.neighbors[a].contains(&b) {
+            self.neighbors[a].push(b);
+            self.neighbors[b].push(a);
+        }
+    }
+
+    fn node(&mut self, id: &str) -> usize {
+        if let Some(&node) = self.index.get(id) {
+            return node;
+        }
+
+        self.ids.push(id.to_owned());
+        self.neighbors.push(vec![]);
+        self.index.insert(id.to_owned(), self.ids.len() - 1);
+
+        self.ids.len() - 1
+    }
+}
+
+fn attribute<'a>(element: &'a str, name: &str) -> Option<&'a str> {
+    let start = element.find(&format!("{name}=\""))? + name.len() + 2;
+    let len = element[start..].find('"')?;
+
+    Some(&element[start..start + len])
+}
+
+/// Placement of caches in the nodes of a topology.
+#[derive(Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TreeSolution {
+    cost: u64,
+    caches: Box<[String]>,
+}
+
+impl TreeSolution {
+    /// Get the solution's cost.
+    pub fn cost(&self) -> u64 {
+        self.cost
+    }
+
+    /// Get a reference to the IDs of the nodes with a cache.
+    pub fn caches(&self) -> &[String] {
+        self.caches.as_ref()
+    }
+}
+
+/// Shortest path tree of a topology, with the nodes in breadth first order.
+#[derive(Debug)]
+struct Tree {
+    order: Vec<usize>,
+    depth: Vec<usize>,
+    children: Vec<Vec<usize>>,
+}
+
+impl Tree {
+    fn create(topology: &Topology, root: usize) -> Self {
+        let mut order = vec![root];
+        let mut depth = vec![usize::MAX; topology.len()];
+        let mut children = vec![vec![]; topology.len()];
+        let mut pending = VecDeque::from([root]);
+
+        depth[root] = 0;
+        while let Some(node) = pending.pop_front() {
+            for &next in &topology.neighbors[node] {
+                if depth[next] == usize::MAX {
+                    depth[next] = depth[node] + 1;
+                    children[node].push(next);
+                    order.push(next);
+                    pending.push_back(next);
+                }
+            }
+        }
+
+        Self {
+            order,
+            depth,
+            children,
+        }
+    }
+}
+
+/// Exact placement by dynamic programming over the shortest path tree.
+///
+/// `costs[node][d][k]` is the minimum cost of the subtree of `node` with at
+/// most `k` caches, when the closest cache or producer above it is `d` hops
+/// away.
+struct TreePlacer<'a> {
+    tree: &'a Tree,
+    ncaches: usize,
+    costs: Vec<Vec<Vec<u64>>>,
+}
+
+impl<'a> TreePlacer<'a> {
+    fn create(tree: &'a Tree, ncaches: usize) -> Self {
+        let mut placer = Self {
+            tree,
+            ncaches,
+            costs: vec![vec![]; tree.depth.len()],
+        };
+
+        for &node in tree.order.iter().rev() {
+            placer.costs[node] = (0..=tree.depth[node])
+                .map(|d| placer.node_costs(node, d))
+                .collect();
+        }
+
+        placer
+    }
+
+    fn node_costs(&self, node: usize, distance: usize) -> Vec<u64> {
+        let (without, with) = self.choices(node, distance);
+
+        without
+            .into_iter()
+            .zip(with)
+            .map(|(without, with)| without.min(with))
+            .collect()
+    }
+
+    /// Costs of the subtree of `node` without and with a cache in it for every
+    /// budget. The producer never gets a cache.
+    fn choices(&self, node: usize, distance: usize) -> (Vec<u64>, Vec<u64>) {
+        if distance == 0 {
+            return (
+                self.combine_children(node, 1),
+                vec![u64::MAX; self.ncaches + 1],
+            );
+        }
+
+        let without = self
+            .combine_children(node, distance + 1)
+            .into_iter()
+            .map(|cost| cost + distance as u64)
+            .collect();
+        let with = [u64::MAX]
+            .into_iter()
+            .chain(self.combine_children(node, 1))
+            .take(self.ncaches + 1)
+            .collect();
+
+        (without, with)
+    }
+
+    fn combine_children(&self, node: usize, distance: usize) -> Vec<u64> {
+        self.tree.children[node]
+            .iter()
+            .fold(vec![0; self.ncaches + 1], |acc, &child| {
+                min_plus(&acc, &self.costs[child][distance])
+            })
+    }
+
+    /// Places the caches of the subtree of `node` following the stored costs.
+    fn place(&self, node: usize, distance: usize, budget: usize, caches: &mut Vec<usize>) {
+        let (without, with) = self.choices(node, distance);
+        let (children_distance, mut budget) = if with[budget] < without[budget] {
+            caches.push(node);
+            (1, budget - 1)
+        } else {
+            (distance + 1, budget)
+        };
+
+        // Undo the combination of the children from the last one
+        let children = &self.tree.children[node];
+        let partials: Vec<_> = children
+            .iter()
+            .scan(vec![0; self.ncaches + 1], |acc, &child| {
+                *acc = min_plus(acc, &self.costs[child][children_distance]);
+                Some(acc.clone())
+            })
+            .collect();
+        for (i, &child) in children.iter().enumerate().rev() {
+            let child_costs = &self.costs[child][children_distance];
+            let child_budget = (0..=budget)
+                .find(|&k| {
+                    let rest = if i > 0 {
+                        partials[i - 1][budget - k]
+                    } else {
+                        0
+                    };
+                    rest.saturating_add(child_costs[k]) == partials[i][budget]
+                })
+                .expect("the combination can always be undone");
+
+            self.place(child, children_distance, child_budget, caches);
+            budget -= child_budget;
+        }
+    }
+}
+
+/// Min-plus convolution of two cost vectors indexed by the number of caches.
+fn min_plus(a: &[u64], b: &[u64]) -> Vec<u64> {
+    (0..a.len())
+        .map(|k| {
+            (0..=k)
+                .map(|i| a[i].saturating_add(b[k - i]))
+                .min()
+                .unwrap_or(u64::MAX)
+        })
+        .collect()
+}
+
+/// Places at most `ncaches` caches in `topology` so that the total number of
+/// hops from every node to its closest cache or to the producer is minimal.
+///
+/// Nodes that cannot reach the producer are ignored.
+pub fn get_tree_cache_locations(
+    topology: &Topology,
+    producer: &str,
+    ncaches: usize,
+) -> Result<TreeSolution, TopologyError> {
+    let root = *topology
+        .index
+        .get(producer)
+        .ok_or_else(|| TopologyError::UnknownProducer(producer.to_owned()))?;
+    let tree = Tree::create(topology, root);
+    let placer = TreePlacer::create(&tree, ncaches);
+
+    let mut caches = vec![];
+    placer.place(root, 0, ncaches, &mut caches);
+
+    let mut caches: Vec<_> = caches
+        .into_iter()
+        .map(|node| topology.ids[node].clone())
+        .collect();
+    caches.sort_unstable();
+
+    Ok(TreeSolution {
+        cost: placer.costs[root][0][ncaches],
+        caches: caches.into_boxed_slice(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use itertools::Itertools;
+
+    use super::{get_tree_cache_locations, Topology, TopologyError, Tree};
+
+    #[test]
+    fn line_with_one_cache() {
+        // p - a - b - c - d - e
+        let topology = Topology::parse("p a\na b\nb c\nc d\nd e\n").unwrap();
+        let sol = get_tree_cache_locations(&topology, "p", 1).unwrap();
+
+        assert_eq!(sol.caches(), ["c"]);
+        assert_eq!(sol.cost(), 6);
+    }
+
+    #[test]
+    fn star_of_chains() {
+        let topology = Topology::parse(
+            "# Two long branches and a short one\n\
+             p a1\na1 a2\na2 a3\n\
+             p b1\nb1 b2\nb2 b3\n\
+             p c1\n",
+        )
+        .unwrap();
+        let sol = get_tree_cache_locations(&topology, "p", 2).unwrap();
+
+        assert_eq!(sol.caches(), ["a2", "b2"]);
+        assert_eq!(sol.cost(), 5);
+    }
+
+    #[test]
+    fn more_caches_than_needed() {
+        let topology = Topology::parse("p a\np b\n").unwrap();
+        let sol = get_tree_cache_locations(&topology, "p", 5).unwrap();
+
+        assert_eq!(sol.cost(), 0);
+        assert_eq!(sol.caches(), ["a", "b"]);
+    }
+
+    #[test]
+    fn grid_matches_brute_force() {
+        let mut text = String::new();
+        for x in 0..4 {
+            for y in 0..4 {
+                if x < 3 {
+                    text += &format!("{x},{y} {},{y}\n", x + 1);
+                }
+                if y < 3 {
+                    text += &format!("{x},{y} {x},{}\n", y + 1);
+                }
+            }
+        }
+        let topology = Topology::parse(&text).unwrap();
+        let sol = get_tree_cache_locations(&topology, "0,0", 2).unwrap();
+
+        let root = topology.index["0,0"];
+        let tree = Tree::create(&topology, root);
+        let mut parent = vec![root; topology.len()];
+        for (node, children) in tree.children.iter().enumerate() {
+            for &child in children {
+                parent[child] = node;
+            }
+        }
+        let cost = |caches: &[usize]| -> u64 {
+            (0..topology.len())
+                .map(|mut node| {
+                    let mut hops = 0;
+                    while node != root && !caches.contains(&node) {
+                        node = parent[node];
+                        hops += 1;
+                    }
+                    hops
+                })
+                .sum()
+        };
+        let best = (0..topology.len())
+            .tuple_combinations()
+            .filter(|&(a, b)| a != root && b != root)
+            .map(|(a, b)| cost(&[a, b]))
+            .min()
+            .unwrap();
+
+        assert_eq!(sol.cost(), best);
+        assert_eq!(sol.caches().len(), 2);
+    }
+
+    #[test]
+    fn graphml_edges() {
+        let topology = Topology::parse(
+            "<graphml>\n<graph>\n<edge source=\"p\" target=\"a\"/>\n\
+             <edge id=\"e1\" source=\"a\" target=\"b\"/>\n</graph>\n</graphml>\n",
+        )
+        .unwrap();
+
+        assert_eq!(topology.len(), 3);
+    }
+
+    #[test]
+    fn errors() {
+        assert!(matches!(
+            Topology::parse("p a\nb\n"),
+            Err(TopologyError::Parse { line: 2 })
+        ));
+
+        let topology = Topology::parse("p a\n").unwrap();
+        assert!(matches!(
+            get_tree_cache_locations(&topology, "q", 1),
+            Err(TopologyError::UnknownProducer(_))
+        ));
+    }
+}
@@ -0,0 +1,323 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+/*
+ *
+ * Copyright (c) 2022 Universidade de Vigo
+ *
+ * This program is free software; you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License version 2 as
+ * published by the Free Software Foundation;
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program; if not, write to the Free Software
+ * Foundation, Inc., 59 Temple Place, Suite 330, Boston, MA  02111-1307  USA
+ *
+ * Author: Miguel Rodríguez Pérez <miguel@det.uvigo.gal>
+ *
+ */
+
+//! Diagnostics of the local search, to follow how it converges.
+
+use std::time::Instant;
+
+use crate::{
+    gridsearcher::{search_both, Layout, Move, Observer, Searcher, Step},
+    Objective, Solution,
+};
+
+/// What happened in a traced search.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventKind {
+    /// The search started at a placement with this objective.
+    Start { objective: u64 },
+    /// A move of the cache at `index` was tried.
+    Try {
+        index: usize,
+        mv: Move<u64>,
+        taken: bool,
+    },
+    /// A move was taken, changing the objective by `delta`.
+    Move {
+        index: usize,
+        mv: Move<u64>,
+        objective: u64,
+        delta: i128,
+    },
+    /// A sweep over all the caches ended.
+    Sweep {
+        sweep: usize,
+        step_size: u64,
+        improved: bool,
+    },
+    /// The search ended.
+    Summary {
+        iterations: usize,
+        moves: usize,
+        sweeps: usize,
+        wall_time_us: u128,
+        cost: u64,
+    },
+}
+
+/// Event of the search of a split by one of the searchers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TraceEvent {
+    pub nhoriz: usize,
+    pub nvert: usize,
+    pub layout: Layout,
+    pub kind: EventKind,
+}
+
+fn signed_step(mv: Move<u64>) -> i128 {
+    match mv {
+        Move::Forward(step) => step.into(),
+        Move::Backward(step) => -i128::from(step),
+    }
+}
+
+impl TraceEvent {
+    /// Encodes the event as a single line of JSON.
+    pub fn to_json(&self) -> String {
+        let kind = match self.kind {
+            EventKind::Start { objective } => {
+                format!(r#""event":"start","objective":{objective}"#)
+            }
+            EventKind::Try { index, mv, taken } => format!(
+                r#""event":"try","index":{index},"step":{},"taken":{taken}"#,
+                signed_step(mv)
+            ),
+            EventKind::Move {
+                index,
+                mv,
+                objective,
+                delta,
+            } => format!(
+                r#""event":"move","index":{index},"step":{},"objective":{objective},"delta":{delta}"#,
+                signed_step(mv)
+            ),
+            EventKind::Sweep {
+                sweep,
+                step_size,
+                improved,
+            } => format!(
+                r#""event":"sweep","sweep":{sweep},"step_size":{step_size},"improved":{improved}"#
+            ),
+            EventKind::Summary {
+                iterations,
+                moves,
+                sweeps,
+                wall_time_us,
+                cost,
+            } => format!(
+                r#""event":"summary","iterations":{iterations},"moves":{moves},"sweeps":{sweeps},"wall_time_us":{wall_time_us},"cost":{cost}"#
+            ),
+        };
+
+        format!(
+            r#"{{"nhoriz":{},"nvert":{},"layout":"{}",{kind}}}"#,
+            self.nhoriz, self.nvert, self.layout
+        )
+    }
+}
+
+/// Observer that forwards every hook of a search to a callback, and then to
+/// `next`, which also decides when the search stops.
+struct Tracer<'a, F: ?Sized> {
+    nhoriz: usize,
+    nvert: usize,
+    layout: Layout,
+    callback: &'a F,
+    next: &'a mut dyn Observer<u64>,
+    objective: u64,
+    iterations: usize,
+    moves: usize,
+    sweeps: usize,
+}
+
+impl<F> Tracer<'_, F>
+where
+    F: Fn(TraceEvent) + ?Sized,
+{
+    fn emit(&self, kind: EventKind) {
+        (self.callback)(TraceEvent {
+            nhoriz: self.nhoriz,
+            nvert: self.nvert,
+            layout: self.layout,
+            kind,
+        })
+    }
+}
+
+impl<F> Observer<u64> for Tracer<'_, F>
+where
+    F: Fn(TraceEvent) + ?Sized,
+{
+    fn started(&mut self, objective: &dyn Fn() -> u64) {
+        let objective = objective();
+        self.objective = objective;
+        self.emit(EventKind::Start { objective });
+        self.next.started(&|| objective);
+    }
+
+    fn tried(&mut self, index: usize, mv: Move<u64>, taken: bool) {
+        self.iterations += 1;
+        self.emit(EventKind::Try { index, mv, taken });
+        self.next.tried(index, mv, taken);
+    }
+
+    fn moved(&mut self, step: &Step<u64>, objective: &dyn Fn() -> u64) {
+        let objective = objective();
+        self.moves += 1;
+        self.emit(EventKind::Move {
+            index: step.index,
+            mv: step.mv,
+            objective,
+            delta: i128::from(objective) - i128::from(self.objective),
+        });
+        self.objective = objective;
+        self.next.moved(step, &|| objective);
+    }
+
+    fn swept(&mut self, step_size: u64, improved: bool) {
+        self.emit(EventKind::Sweep {
+            sweep: self.sweeps,
+            step_size,
+            improved,
+        });
+        self.sweeps += 1;
+        self.next.swept(step_size, improved);
+    }
+
+    fn should_stop(&mut self) -> bool {
+        self.next.should_stop()
+    }
+}
+
+/// Runs the search of `searcher`, calling `callback` with every event and
+/// passing the hooks on to `next`.
+pub(crate) fn trace<F>(
+    searcher: &mut dyn Searcher<u64>,
+    layout: Layout,
+    nhoriz: usize,
+    nvert: usize,
+    callback: &F,
+    next: &mut dyn Observer<u64>,
+) -> Solution<u64>
+where
+    F: Fn(TraceEvent) + ?Sized,
+{
+    let start = Instant::now();
+    let mut tracer = Tracer {
+        nhoriz,
+        nvert,
+        layout,
+        callback,
+        next,
+        objective: 0,
+        iterations: 0,
+        moves: 0,
+        sweeps: 0,
+    };
+    let sol = searcher.find_solution_observed(&mut tracer);
+
+    tracer.emit(EventKind::Summary {
+        iterations: tracer.iterations,
+        moves: tracer.moves,
+        sweeps: tracer.sweeps,
+        wall_time_us: start.elapsed().as_micros(),
+        cost: sol.cost(),
+    });
+
+    sol
+}
+
+/// Like [`get_objective_cache_locations`](crate::gridsearcher::get_objective_cache_locations),
+/// but calling `callback` with every event of both searchers. The callback is
+/// called from both searchers at the same time.
+pub fn get_traced_cache_locations<F>(
+    width: u64,
+    height: u64,
+    nhoriz: usize,
+    nvert: usize,
+    objective: Objective,
+    callback: &F,
+) -> Solution<u64>
+where
+    F: Fn(TraceEvent) + Sync,
+{
+    search_both(
+        width,
+        height,
+        nhoriz,
+        nvert,
+        objective,
+        |searcher, layout| trace(searcher, layout, nhoriz, nvert, callback, &mut ()),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use crate::{get_cache_locations, Objective};
+
+    use crate::gridsearcher::Layout;
+
+    use super::{get_traced_cache_locations, EventKind, TraceEvent};
+
+    #[test]
+    fn trace_matches_search() {
+        let events = Mutex::new(Vec::new());
+        let sol = get_traced_cache_locations(100, 60, 3, 1, Objective::Nominal, &|event| {
+            events.lock().unwrap().push(event)
+        });
+        let events = events.into_inner().unwrap();
+
+        assert_eq!(sol, get_cache_locations(100, 60, 3, 1));
+        for layout in [Layout::HorizontalFirst, Layout::VerticalFirst] {
+            let events: Vec<_> = events.iter().filter(|e| e.layout == layout).collect();
+            let tries = events
+                .iter()
+                .filter(|e| matches!(e.kind, EventKind::Try { .. }))
+                .count();
+            let deltas: i128 = events
+                .iter()
+                .filter_map(|e| match e.kind {
+                    EventKind::Move { delta, .. } => Some(delta),
+                    _ => None,
+                })
+                .sum();
+
+            assert!(matches!(events[0].kind, EventKind::Start { .. }));
+            assert!(deltas <= 0);
+            match events.last().unwrap().kind {
+                EventKind::Summary { iterations, .. } => assert_eq!(iterations, tries),
+                kind => panic!("Last event is {kind:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn json_lines() {
+        let event = TraceEvent {
+            nhoriz: 3,
+            nvert: 1,
+            layout: Layout::VerticalFirst,
+            kind: EventKind::Move {
+                index: 2,
+                mv: crate::gridsearcher::Move::Backward(4),
+                objective: 1000,
+                delta: -12,
+            },
+        };
+
+        assert_eq!(
+            event.to_json(),
+            r#"{"nhoriz":3,"nvert":1,"layout":"vertical-first","event":"move","index":2,"step":-4,"objective":1000,"delta":-12}"#
+        );
+    }
+}